@@ -1,10 +1,10 @@
-use clap::{Args, Parser, Subcommand};
+use clap::{ArgAction, Args, Parser, Subcommand};
+use std::time::Duration;
 
 #[derive(Debug, Parser)]
 #[clap(name = "smriti")]
 #[clap(about = "Keep track of the commands your memory missed.")]
 #[clap(version = "1.0")]
-
 pub struct SmritiCli {
     #[clap(subcommand)]
     pub command: CliArgs,
@@ -26,30 +26,236 @@ pub enum CliArgs {
     Show(ShowCommand),
     /// Update an existing alias
     Rename(RenameCommand),
+    /// Manage global and per-service variables used by placeholders.
+    Var(VarCommand),
+    /// Show the arguments a saved command expects.
+    Usage(UsageCommand),
+    /// List or forget placeholder values remembered from previous runs.
+    History(HistoryCommand),
+    /// List captured runs and print their output.
+    Logs(LogsCommand),
+    /// Manage smriti settings such as the default shell.
+    Config(ConfigCommand),
+    /// Run saved commands on a cron schedule, see `smriti scheduler`.
+    Schedule(ScheduleCommand),
+    /// Run scheduled commands when they are due, until stopped with Ctrl-C.
+    Scheduler,
+    /// Print completion candidates for shell integrations.
+    #[command(hide = true)]
+    Complete(CompleteCommand),
 }
 
-#[derive(Debug, Args)]
+#[derive(Debug, Clone, Default, Args)]
+#[command(disable_help_flag = true)]
 pub struct RunCommand {
-    #[arg(value_name = "ALIAS")]
-    pub alias: String,
+    #[arg(value_name = "ALIAS", required_unless_present_any = ["help", "service"])]
+    pub alias: Option<String>,
     #[arg(value_name = "VARIABLES")]
     pub variables: Vec<String>,
+    /// Run every command of this service instead of one alias, in the order they were added
+    #[arg(long, value_name = "SERVICE", conflicts_with = "alias")]
+    pub service: Option<String>,
+    /// Run up to this many commands of the service at the same time [default: 1]
+    #[arg(long, value_name = "N", requires = "service", conflicts_with = "alias",
+          value_parser = clap::value_parser!(u16).range(1..))]
+    pub parallel: Option<u16>,
+    /// Set a placeholder by name instead of by position
+    #[arg(long = "set", value_name = "NAME=VALUE", value_parser = parse_key_val)]
+    pub set: Vec<(String, String)>,
+    /// Print each placeholder value and where it came from before running
+    #[arg(long)]
+    pub show_resolved: bool,
+    /// Do not remember the placeholder values of this run
+    #[arg(long)]
+    pub no_history: bool,
+    /// Also store the output of this run, see `smriti logs` (ignored for interactive commands)
+    #[arg(long)]
+    pub capture: bool,
+    /// Run in this directory instead of the command's own
+    #[arg(long, value_name = "DIR")]
+    pub cwd: Option<String>,
+    /// Set an environment variable for this run, overriding the command's own
+    #[arg(long, value_name = "NAME=VALUE", value_parser = parse_key_val)]
+    pub env: Vec<(String, String)>,
+    /// Run without inheriting the environment
+    #[arg(long)]
+    pub clear_env: bool,
+    /// Run without asking, even when the command needs confirmation
+    #[arg(short, long)]
+    pub yes: bool,
+    /// Show the resolved command, interpreter, directory and environment without running it
+    #[arg(long, conflicts_with_all = ["print", "capture"])]
+    pub dry_run: bool,
+    /// Only write the resolved command to stdout, e.g. for `eval "$(smriti run ... --print)"`
+    #[arg(long, conflicts_with = "capture")]
+    pub print: bool,
+    /// Stop the command after this long instead of its own timeout, 0 for none
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub timeout: Option<Duration>,
+    /// Retry a failed command this many times instead of its own number of retries
+    #[arg(long, value_name = "N")]
+    pub retries: Option<u32>,
+    /// Wait before the first retry instead of the command's own delay
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub retry_delay: Option<Duration>,
+    /// Run again whenever a file matching this glob changes, e.g. 'src/**/*.rs' (repeatable)
+    #[arg(long, value_name = "GLOB", conflicts_with_all = ["service", "dry_run", "print"])]
+    pub watch: Vec<String>,
+    /// Clear the screen before each run in watch mode, instead of printing a separator
+    #[arg(long, requires = "watch")]
+    pub clear: bool,
+    /// Print help, or the usage of ALIAS when one is given
+    #[arg(short, long, action = ArgAction::SetTrue)]
+    pub help: bool,
+}
+
+pub fn parse_key_val(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("invalid NAME=VALUE: no `=` found in `{}`", s)),
+    }
+}
+
+/// Parses durations such as `500ms`, `30s`, `5m` or `1h`; plain numbers are seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: u64 = number.parse().map_err(|_| {
+        format!(
+            "invalid duration `{}`: expected e.g. 500ms, 30s, 5m or 1h",
+            s
+        )
+    })?;
+    let seconds = |factor: u64| number.checked_mul(factor).map(Duration::from_secs);
+    let duration = match unit {
+        "ms" => Some(Duration::from_millis(number)),
+        "" | "s" => seconds(1),
+        "m" => seconds(60),
+        "h" => seconds(3600),
+        _ => {
+            return Err(format!(
+                "invalid duration unit `{}`: expected ms, s, m or h",
+                unit
+            ))
+        }
+    };
+    // Durations are saved in milliseconds
+    duration
+        .filter(|duration| i64::try_from(duration.as_millis()).is_ok())
+        .ok_or_else(|| format!("invalid duration `{}`: too long", s))
+}
+
+/// Formats a duration the way `parse_duration` reads it, e.g. `1m30s` or `250ms`.
+pub fn format_duration(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    if millis == 0 {
+        return "0s".to_string();
+    }
+    if !millis.is_multiple_of(1000) {
+        return format!("{}ms", millis);
+    }
+    let (hours, minutes, seconds) = (millis / 3_600_000, millis / 60_000 % 60, millis / 1000 % 60);
+    let mut formatted = String::new();
+    for (value, unit) in [(hours, "h"), (minutes, "m"), (seconds, "s")] {
+        if value > 0 {
+            formatted.push_str(&format!("{}{}", value, unit));
+        }
+    }
+    formatted
+}
+
+pub fn parse_key_column(s: &str) -> Result<(String, usize), String> {
+    let (key, value) = parse_key_val(s)?;
+    match value.parse::<usize>() {
+        Ok(column) if column > 0 => Ok((key, column)),
+        _ => Err(format!(
+            "invalid column `{}`: expected a number from 1",
+            value
+        )),
+    }
 }
 
 #[derive(Debug, Args)]
 pub struct AddCommand {
     /// Command to save
-    #[arg(short, long)]
+    #[arg(
+        short,
+        long,
+        required_unless_present = "edit",
+        default_value = "",
+        hide_default_value = true
+    )]
     pub command: String,
     /// Command alias
-    #[arg(short, long)]
+    #[arg(
+        short,
+        long,
+        required_unless_present = "edit",
+        default_value = "",
+        hide_default_value = true
+    )]
     pub alias: String,
     /// Command description
     #[arg(short, long)]
     pub info: Option<String>, // Optional, update only if provided
     /// Groups command according to different type or stack
-    #[arg(short, long)]
+    #[arg(
+        short,
+        long,
+        required_unless_present = "edit",
+        default_value = "",
+        hide_default_value = true
+    )]
     pub service: String,
+    /// Write a multi-line script in $EDITOR, with the alias, info and service in its header
+    #[arg(long, conflicts_with = "workflow")]
+    pub edit: bool,
+    /// Render the command with the template engine ({{ var }}, {% if %}, {% for %})
+    #[arg(long)]
+    pub template: bool,
+    /// Turn IP addresses, ports, URLs and paths into placeholders with the original values as defaults
+    #[arg(long, conflicts_with = "template")]
+    pub parameterize: bool,
+    /// The command lists saved aliases to run in order, e.g. "test; -lint; push {tag}"
+    #[arg(long, conflicts_with_all = ["template", "parameterize", "shell", "interactive"])]
+    pub workflow: bool,
+    /// Always run attached to the terminal, for programs like ssh, vim or psql
+    #[arg(long)]
+    pub interactive: bool,
+    /// Run with this interpreter instead of the default shell, e.g. bash, zsh, fish or python3
+    #[arg(long, visible_alias = "interpreter", value_name = "INTERPRETER")]
+    pub shell: Option<String>,
+    /// Directory to run the command in, may start with ~ and use $VARIABLES
+    #[arg(long, value_name = "DIR")]
+    pub cwd: Option<String>,
+    /// Environment variable to set when the command runs, the value may use placeholders
+    #[arg(long, value_name = "NAME=VALUE", value_parser = parse_key_val)]
+    pub env: Vec<(String, String)>,
+    /// Run without inheriting the environment, only with the variables given by --env
+    #[arg(long)]
+    pub clear_env: bool,
+    /// Ask for confirmation before every run, also set for commands matching the danger patterns
+    #[arg(long)]
+    pub confirm: bool,
+    /// Stop the command and its child processes when it runs longer than this, e.g. 30s or 5m
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub timeout: Option<Duration>,
+    /// Run the command again up to this many times when it fails or times out
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub retries: u32,
+    /// Wait before the first retry, doubled before each following one [default: 1s]
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub retry_delay: Option<Duration>,
+    /// Shell command whose output lists the choices for a placeholder
+    #[arg(long, value_name = "NAME=COMMAND", value_parser = parse_key_val)]
+    pub generator: Vec<(String, String)>,
+    /// Only offer this whitespace-separated column (1-based) of the generator output
+    #[arg(long, value_name = "NAME=N", value_parser = parse_key_column)]
+    pub column: Vec<(String, usize)>,
+    /// Describe a placeholder for `smriti usage`
+    #[arg(long, value_name = "NAME=TEXT", value_parser = parse_key_val)]
+    pub describe: Vec<(String, String)>,
 }
 
 #[derive(Debug, Args)]
@@ -67,6 +273,9 @@ pub struct UpdateCommand {
     /// Update existing commands
     #[arg(short, long)]
     pub command: Option<String>,
+    /// Edit the command as a multi-line script in $EDITOR
+    #[arg(long, conflicts_with = "command")]
+    pub edit: bool,
     /// Updating existing command alias
     #[arg(short, long)]
     pub alias: String,
@@ -76,6 +285,51 @@ pub struct UpdateCommand {
     /// Update command service
     #[arg(short, long)]
     pub service: Option<String>,
+    /// Turn template mode on or off
+    #[arg(long, value_name = "BOOL")]
+    pub template: Option<bool>,
+    /// Whether the command is a workflow of other aliases
+    #[arg(long, value_name = "BOOL")]
+    pub workflow: Option<bool>,
+    /// Mark the command as interactive or not
+    #[arg(long, value_name = "BOOL")]
+    pub interactive: Option<bool>,
+    /// Change the interpreter, or pass "" to use the default shell again
+    #[arg(long, visible_alias = "interpreter", value_name = "INTERPRETER")]
+    pub shell: Option<String>,
+    /// Change the working directory, or pass "" to run in the current directory
+    #[arg(long, value_name = "DIR")]
+    pub cwd: Option<String>,
+    /// Set an environment variable of the command, the value may use placeholders
+    #[arg(long, value_name = "NAME=VALUE", value_parser = parse_key_val)]
+    pub env: Vec<(String, String)>,
+    /// Remove an environment variable of the command
+    #[arg(long, value_name = "NAME")]
+    pub unset_env: Vec<String>,
+    /// Whether the command runs without inheriting the environment
+    #[arg(long, value_name = "BOOL")]
+    pub clear_env: Option<bool>,
+    /// Whether the command asks for confirmation before it runs
+    #[arg(long, value_name = "BOOL")]
+    pub confirm: Option<bool>,
+    /// Change the timeout, or pass 0 to let the command run as long as it takes
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub timeout: Option<Duration>,
+    /// Change how many times a failed command is run again
+    #[arg(long, value_name = "N")]
+    pub retries: Option<u32>,
+    /// Change the wait before the first retry
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub retry_delay: Option<Duration>,
+    /// Shell command whose output lists the choices for a placeholder
+    #[arg(long, value_name = "NAME=COMMAND", value_parser = parse_key_val)]
+    pub generator: Vec<(String, String)>,
+    /// Only offer this whitespace-separated column (1-based) of the generator output
+    #[arg(long, value_name = "NAME=N", value_parser = parse_key_column)]
+    pub column: Vec<(String, usize)>,
+    /// Describe a placeholder for `smriti usage`
+    #[arg(long, value_name = "NAME=TEXT", value_parser = parse_key_val)]
+    pub describe: Vec<(String, String)>,
 }
 
 #[derive(Debug, Args)]
@@ -106,3 +360,138 @@ pub struct RenameCommand {
     pub alias: String,
     pub new_alias: String,
 }
+
+#[derive(Debug, Args)]
+pub struct VarCommand {
+    #[clap(subcommand)]
+    pub action: VarAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum VarAction {
+    /// Create or overwrite a variable
+    Set {
+        name: String,
+        value: String,
+        /// Scope the variable to a service
+        #[arg(short, long)]
+        service: Option<String>,
+    },
+    /// Print the value of a variable
+    Get {
+        name: String,
+        /// Read the variable scoped to a service
+        #[arg(short, long)]
+        service: Option<String>,
+    },
+    /// List saved variables
+    List {
+        /// Only list variables scoped to a service
+        #[arg(short, long)]
+        service: Option<String>,
+    },
+    /// Remove a variable
+    Unset {
+        name: String,
+        /// Remove the variable scoped to a service
+        #[arg(short, long)]
+        service: Option<String>,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigCommand {
+    #[clap(subcommand)]
+    pub action: ConfigAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigAction {
+    /// Change a setting
+    Set { key: String, value: String },
+    /// Print the value of a setting
+    Get { key: String },
+    /// List the settings and their values
+    List,
+    /// Reset a setting to its default
+    Unset { key: String },
+}
+
+#[derive(Debug, Args)]
+pub struct UsageCommand {
+    pub alias: String,
+}
+
+#[derive(Debug, Args)]
+pub struct HistoryCommand {
+    #[clap(subcommand)]
+    pub action: HistoryAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HistoryAction {
+    /// List remembered values, most used first
+    List {
+        alias: String,
+        /// Only list values of this placeholder
+        #[arg(short, long)]
+        placeholder: Option<String>,
+    },
+    /// Forget remembered values, e.g. secrets passed by mistake
+    Forget {
+        alias: String,
+        /// Only forget values of this placeholder
+        #[arg(short, long)]
+        placeholder: Option<String>,
+        /// Only forget this value
+        value: Option<String>,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct ScheduleCommand {
+    #[clap(subcommand)]
+    pub action: ScheduleAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ScheduleAction {
+    /// Run a command on a schedule, replacing its current one
+    Add {
+        alias: String,
+        /// When to run, e.g. "*/15 * * * *" or "30 9 * * mon-fri", in local time
+        #[arg(long)]
+        cron: String,
+        /// What to do with runs missed while the scheduler was not running
+        #[arg(long, default_value = "skip", value_parser = ["skip", "once", "all"])]
+        missed: String,
+        /// Schedule without asking, even when the command needs confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// List schedules and when they run next
+    List,
+    /// Stop running a command on a schedule
+    Remove { alias: String },
+}
+
+#[derive(Debug, Args)]
+pub struct LogsCommand {
+    /// Only list runs of this alias
+    pub alias: Option<String>,
+    /// Print the command and output of this run
+    #[arg(long)]
+    pub id: Option<i64>,
+    /// Number of runs to list
+    #[arg(short = 'n', long, default_value_t = 10)]
+    pub limit: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct CompleteCommand {
+    /// Complete placeholder values of this alias instead of aliases
+    pub alias: Option<String>,
+    /// Index of the positional placeholder being completed
+    #[arg(long, default_value_t = 0)]
+    pub position: usize,
+}
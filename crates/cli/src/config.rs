@@ -0,0 +1,65 @@
+use crate::shell_executor;
+use database::get_setting;
+use regex::Regex;
+use rusqlite::Connection;
+use std::error::Error;
+
+/// Interpreter for commands that do not set their own.
+pub const SHELL: &str = "shell";
+/// Regular expression matching commands that need confirmation before they run.
+pub const DANGER_PATTERNS: &str = "danger_patterns";
+
+/// Settings `smriti config` accepts, with what they are for and their default.
+pub const SETTINGS: &[(&str, &str, &str)] = &[
+    (
+        SHELL,
+        "Interpreter for commands without one of their own",
+        "sh",
+    ),
+    (
+        DANGER_PATTERNS,
+        "Commands matching this regex need confirmation, use | to list several",
+        "(built-in list)",
+    ),
+];
+
+// Matched case-insensitively against the command when it is added.
+const DEFAULT_DANGER_PATTERNS: &str = concat!(
+    r"\brm\s+(-[a-z]*[rf][a-z]*\s+)+",
+    r"|\bkubectl\s+(delete|drain)\b",
+    r"|\bdrop\s+(table|database|schema)\b",
+    r"|\btruncate\s+table\b",
+    r"|\bgit\s+(push\s.*(--force|\s-f\b)|reset\s+--hard|clean\s+-[a-z]*f)",
+    r"|\bterraform\s+destroy\b",
+    r"|\bdocker\s+(system|volume|image)\s+prune\b",
+    r"|\b(mkfs|shred|shutdown|reboot)\b",
+    r"|\bdd\s+if=",
+    r"|\bchmod\s+-R\s+777\b",
+    r"|>\s*/dev/sd",
+);
+
+/// Whether `key` is a setting smriti knows about.
+pub fn is_setting(key: &str) -> bool {
+    SETTINGS.iter().any(|(name, _, _)| *name == key)
+}
+
+/// Checks a value before it is saved for `key`.
+pub fn check_setting(key: &str, value: &str) -> Result<(), Box<dyn Error>> {
+    match key {
+        SHELL => shell_executor::check_interpreter(value),
+        DANGER_PATTERNS => Regex::new(value)
+            .map(|_| ())
+            .map_err(|e| format!("Invalid danger pattern: {}", e).into()),
+        _ if is_setting(key) => Ok(()),
+        _ => Err(format!("Unknown setting: {}", key).into()),
+    }
+}
+
+/// Whether a command matches the configured danger patterns.
+pub fn is_dangerous(conn: &Connection, command: &str) -> bool {
+    let patterns =
+        get_setting(conn, DANGER_PATTERNS).unwrap_or_else(|_| DEFAULT_DANGER_PATTERNS.to_string());
+    Regex::new(&format!("(?i){}", patterns))
+        .map(|danger| danger.is_match(command))
+        .unwrap_or(false)
+}
@@ -0,0 +1,145 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike};
+use std::error::Error;
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+// How far ahead to look for the next time, past every day-of-month and
+// weekday combination, so that e.g. `0 0 30 2 *` is known never to run.
+const SEARCH_YEARS: i32 = 30;
+
+/// A cron expression: minute, hour, day of month, month and day of week.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    // With both day fields restricted a day matching either one is enough, as in cron
+    any_day: bool,
+    any_weekday: bool,
+}
+
+// Parses one field into a bit set of the values it allows, e.g. `1-5`,
+// `*/15`, `mon,wed` or `10-40/10`.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let value = |text: &str| -> Result<u32, String> {
+        let value = match names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(text))
+        {
+            // Month names count from 1, weekday names from 0
+            Some(index) => index as u32 + min,
+            None => text
+                .parse()
+                .map_err(|_| format!("invalid value `{}` in `{}`", text, field))?,
+        };
+        match (min..=max).contains(&value) {
+            true => Ok(value),
+            false => Err(format!(
+                "`{}` in `{}` is not between {} and {}",
+                value, field, min, max
+            )),
+        }
+    };
+
+    let mut set = 0;
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(format!("invalid step `{}` in `{}`", step, field)),
+            },
+            None => (item, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (value(start)?, value(end)?),
+            // `5/15` runs from 5 to the end of the range
+            None if step > 1 => (value(range)?, max),
+            None => (value(range)?, value(range)?),
+        };
+        if start > end {
+            return Err(format!("range `{}` in `{}` is backwards", range, field));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+impl Cron {
+    /// Parses five fields such as `*/15 * * * *` or `30 9 * * mon-fri`, or
+    /// one of `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly`.
+    pub fn parse(expression: &str) -> Result<Cron, Box<dyn Error>> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            expression => expression,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!(
+                "Expected 5 fields (minute hour day month weekday) in `{}`, found {}",
+                expression,
+                fields.len()
+            )
+            .into());
+        };
+
+        let weekdays = parse_field(weekday, 0, 7, &WEEKDAYS)?;
+        let cron = Cron {
+            minutes: parse_field(minute, 0, 59, &[])?,
+            hours: parse_field(hour, 0, 23, &[])? as u32,
+            days: parse_field(day, 1, 31, &[])? as u32,
+            months: parse_field(month, 1, 12, &MONTHS)? as u16,
+            // 7 is Sunday too
+            weekdays: ((weekdays | weekdays >> 7) & 0x7f) as u8,
+            any_day: day.starts_with('*'),
+            any_weekday: weekday.starts_with('*'),
+        };
+        Ok(cron)
+    }
+
+    fn runs_on(&self, date: NaiveDate) -> bool {
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    /// The first time after `time` the expression matches, to the minute.
+    pub fn next_after(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut next = time.with_second(0)?.with_nanosecond(0)? + TimeDelta::minutes(1);
+        let limit = time.year() + SEARCH_YEARS;
+
+        while next.year() <= limit {
+            let date = next.date();
+            if self.months & (1 << date.month()) == 0 {
+                let (year, month) = match date.month() {
+                    12 => (date.year() + 1, 1),
+                    month => (date.year(), month + 1),
+                };
+                next = NaiveDate::from_ymd_opt(year, month, 1)?.and_time(NaiveTime::MIN);
+            } else if !self.runs_on(date) {
+                next = date.succ_opt()?.and_time(NaiveTime::MIN);
+            } else if self.hours & (1 << next.hour()) == 0 {
+                next = next.with_minute(0)? + TimeDelta::hours(1);
+            } else if self.minutes & (1 << next.minute()) == 0 {
+                next += TimeDelta::minutes(1);
+            } else {
+                return Some(next);
+            }
+        }
+        None
+    }
+}
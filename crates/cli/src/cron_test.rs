@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod tests {
+    use crate::cron::*;
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    fn at(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn next(expression: &str, after: &str) -> Option<String> {
+        Cron::parse(expression)
+            .unwrap()
+            .next_after(at(after))
+            .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+    }
+
+    #[test]
+    fn test_parse_cron() {
+        assert!(Cron::parse("*/15 * * * *").is_ok());
+        assert!(Cron::parse("0 9-17/2 * jan,jul MON-fri").is_ok());
+        assert_eq!(
+            Cron::parse("@daily").unwrap(),
+            Cron::parse("0 0 * * *").unwrap()
+        );
+        assert_eq!(
+            Cron::parse("0 0 * * 7").unwrap(),
+            Cron::parse("0 0 * * sun").unwrap()
+        );
+
+        assert!(Cron::parse("* * * *").is_err());
+        assert!(Cron::parse("60 * * * *").is_err());
+        assert!(Cron::parse("* * 0 * *").is_err());
+        assert!(Cron::parse("*/0 * * * *").is_err());
+        assert!(Cron::parse("5-1 * * * *").is_err());
+        assert!(Cron::parse("* * * foo *").is_err());
+    }
+
+    #[test]
+    fn test_next_after() {
+        assert_eq!(
+            next("*/15 * * * *", "2026-10-19 09:05").as_deref(),
+            Some("2026-10-19 09:15")
+        );
+        assert_eq!(
+            next("*/15 * * * *", "2026-10-19 09:45").as_deref(),
+            Some("2026-10-19 10:00")
+        );
+        assert_eq!(
+            next("30 9 * * mon-fri", "2026-10-23 10:00").as_deref(),
+            Some("2026-10-26 09:30")
+        );
+        assert_eq!(
+            next("0 0 1 1 *", "2026-10-19 09:05").as_deref(),
+            Some("2027-01-01 00:00")
+        );
+        assert_eq!(
+            next("0 12 29 2 *", "2026-03-01 00:00").as_deref(),
+            Some("2028-02-29 12:00")
+        );
+        // With both days restricted either one is enough
+        assert_eq!(
+            next("0 0 13 * fri", "2026-10-19 00:00").as_deref(),
+            Some("2026-10-23 00:00")
+        );
+        assert_eq!(next("0 0 30 2 *", "2026-10-19 00:00"), None);
+
+        // Strictly after, even within the same minute
+        let time = NaiveDate::from_ymd_opt(2026, 10, 19)
+            .unwrap()
+            .and_hms_opt(9, 15, 30)
+            .unwrap();
+        let cron = Cron::parse("*/15 * * * *").unwrap();
+        assert_eq!(cron.next_after(time), Some(at("2026-10-19 09:30")));
+    }
+}
@@ -0,0 +1,94 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::process::Command;
+
+// Header lines start with this, and are removed from the script when saving.
+const HEADER: &str = "# smriti";
+
+/// A script and the details of the command it is saved as, as written in the editor.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ScriptFile {
+    pub alias: String,
+    pub info: Option<String>,
+    pub service: String,
+    pub script: String,
+}
+
+/// Writes out the text the editor is opened with: a header for the alias,
+/// info and service, followed by the script.
+pub fn script_file(file: &ScriptFile) -> String {
+    format!(
+        "{header} alias: {}\n\
+         {header} info: {}\n\
+         {header} service: {}\n\
+         {header}\n\
+         {header} Lines starting with `{header}` at the top are removed when saving.\n\
+         {header} Start the script with #! to run it with another interpreter.\n\
+         {}",
+        file.alias,
+        file.info.as_deref().unwrap_or_default(),
+        file.service,
+        file.script,
+        header = HEADER,
+    )
+}
+
+/// Reads back a file written by [`script_file`]. The header is taken off the
+/// top and everything after it is the script, kept as written.
+pub fn parse_script_file(text: &str) -> ScriptFile {
+    let mut file = ScriptFile::default();
+    let mut rest = text;
+
+    while let Some(line) = rest.strip_prefix(HEADER) {
+        let (line, next) = line.split_once('\n').unwrap_or((line, ""));
+        rest = next;
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim().to_string();
+            match key.trim() {
+                "alias" => file.alias = value,
+                "info" => file.info = Some(value).filter(|info| !info.is_empty()),
+                "service" => file.service = value,
+                _ => {}
+            }
+        }
+    }
+    file.script = rest.to_string();
+    file
+}
+
+/// Opens `$VISUAL` or `$EDITOR`, or `vi`, on the text and returns it as saved.
+pub fn edit(text: &str) -> Result<String, Box<dyn Error>> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+
+    let path = env::temp_dir().join(format!("smriti-{}.sh", uuid::Uuid::new_v4()));
+    // Scripts often hold credentials, so only the user may read them
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)?
+        .write_all(text.as_bytes())?;
+
+    // Through the shell, as editors are often set with flags, e.g. `code --wait`
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(&path)
+        .status();
+    let edited = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+
+    match status {
+        Ok(status) if status.success() => Ok(edited?),
+        Ok(status) => Err(format!("Editor `{}` exited with {}", editor, status).into()),
+        Err(e) => Err(format!("Could not start editor `{}`: {}", editor, e).into()),
+    }
+}
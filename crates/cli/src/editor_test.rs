@@ -0,0 +1,32 @@
+#[cfg(test)]
+mod tests {
+    use crate::editor::*;
+
+    #[test]
+    fn test_script_file_round_trip() {
+        let file = ScriptFile {
+            alias: "deploy".to_string(),
+            info: Some("Build and ship: web".to_string()),
+            service: "web".to_string(),
+            script: "#!/usr/bin/env bash\nset -e\n\n# build\nmake 'all'\n".to_string(),
+        };
+        let text = script_file(&file);
+        assert!(text.starts_with("# smriti alias: deploy\n"));
+        assert_eq!(parse_script_file(&text), file);
+    }
+
+    #[test]
+    fn test_parse_script_file() {
+        let file =
+            parse_script_file("# smriti alias:  lint \n# smriti info:\n# smriti note\nruff .");
+        assert_eq!(file.alias, "lint");
+        assert_eq!(file.info, None);
+        assert_eq!(file.service, "");
+        assert_eq!(file.script, "ruff .");
+
+        // Only the header at the top is taken off
+        let file = parse_script_file("echo hi\n# smriti alias: other\n");
+        assert_eq!(file.alias, "");
+        assert_eq!(file.script, "echo hi\n# smriti alias: other\n");
+    }
+}
@@ -0,0 +1,19 @@
+//! Exit codes of smriti.
+//!
+//! A command that ran exits with its own code, or 128 + the signal that killed
+//! it, like a shell does. smriti's own errors use the codes below.
+
+/// The command failed without an exit code of its own.
+pub const FAILURE: i32 = 1;
+/// Placeholder values, or options given to smriti, are missing or invalid.
+pub const USAGE: i32 = 64;
+/// No command is saved under the alias.
+pub const UNKNOWN_ALIAS: i32 = 66;
+/// The command needs confirmation and was not confirmed.
+pub const NOT_CONFIRMED: i32 = 77;
+/// The database could not be opened, read or written.
+pub const DATABASE: i32 = 74;
+/// The command ran past its timeout and was stopped.
+pub const TIMEOUT: i32 = 124;
+/// The command could not be started.
+pub const CANNOT_EXECUTE: i32 = 126;
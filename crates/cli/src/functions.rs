@@ -0,0 +1,64 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::Local;
+use std::env;
+use std::error::Error;
+use std::path::Path;
+use subprocess::{Exec, Redirection};
+
+/// Evaluates a built-in placeholder such as `@date:%Y-%m-%d` or `@random:8`.
+/// `@cwd` and `@git_branch` look at `cwd`, the directory the command runs in,
+/// or the current directory when it has none.
+pub fn evaluate(call: &str, cwd: Option<&Path>) -> Result<String, Box<dyn Error>> {
+    let (name, arg) = match call.trim_start_matches('@').split_once(':') {
+        Some((name, arg)) => (name, Some(arg)),
+        None => (call.trim_start_matches('@'), None),
+    };
+
+    match name {
+        "date" => date(arg.unwrap_or("%Y-%m-%d")),
+        "uuid" => Ok(uuid::Uuid::new_v4().to_string()),
+        "cwd" => match cwd {
+            Some(cwd) => Ok(cwd.display().to_string()),
+            None => Ok(env::current_dir()?.display().to_string()),
+        },
+        "git_branch" => git_branch(cwd),
+        "hostname" => Ok(gethostname::gethostname().to_string_lossy().to_string()),
+        "random" => {
+            let len = match arg {
+                Some(len) => len
+                    .parse::<usize>()
+                    .map_err(|_| format!("{{@random:{}}} needs a length", len))?,
+                None => 16,
+            };
+            Ok((0..len).map(|_| fastrand::alphanumeric()).collect())
+        }
+        _ => Err(format!("Unknown built-in placeholder {{@{}}}", name).into()),
+    }
+}
+
+fn date(format: &str) -> Result<String, Box<dyn Error>> {
+    // chrono panics when displaying an invalid format, so check it first
+    let items: Vec<Item> = StrftimeItems::new(format).collect();
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        return Err(format!("Invalid date format {:?}", format).into());
+    }
+    Ok(Local::now()
+        .format_with_items(items.into_iter())
+        .to_string())
+}
+
+fn git_branch(cwd: Option<&Path>) -> Result<String, Box<dyn Error>> {
+    let mut exec = Exec::cmd("git")
+        .args(&["rev-parse", "--abbrev-ref", "HEAD"])
+        .stdout(Redirection::Pipe)
+        .stderr(Redirection::Pipe);
+    if let Some(cwd) = cwd {
+        exec = exec.cwd(cwd);
+    }
+    let result = exec.capture()?;
+
+    if !result.success() {
+        return Err("{@git_branch} needs to run inside a git repository".into());
+    }
+    Ok(String::from_utf8_lossy(&result.stdout).trim().to_string())
+}
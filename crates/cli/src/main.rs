@@ -1,19 +1,1894 @@
-use clap::Parser;
-use cli::{CliArgs, SmritiCli};
+use chrono::TimeZone;
+use clap::{CommandFactory, Parser};
+use cli::{
+    format_duration, CliArgs, ConfigAction, HistoryAction, LogsCommand, RunCommand, ScheduleAction,
+    SmritiCli, VarAction,
+};
 use database::{
-    create_conn, delete_by_alias, delete_by_service, display_by_type, display_commands,
-    insert_command, rename_alias, retrieve_command, retrieve_command_by_alias,
-    retrieve_commands_by_service, update_command_by_alias, update_info_by_alias,
-    update_service_by_alias,
+    create_conn, delete_by_alias, delete_by_service, delete_schedule, display_by_type,
+    display_commands, forget_placeholder_values, get_setting, get_variable, insert_command,
+    list_settings, list_variables, record_placeholder_value, record_run, rename_alias,
+    retrieve_command_by_alias, retrieve_command_env, retrieve_commands_by_service,
+    retrieve_placeholder_history, retrieve_placeholder_specs, retrieve_run, retrieve_runs,
+    retrieve_schedules, set_command_env, set_placeholder_column, set_placeholder_description,
+    set_placeholder_generator, set_schedule, set_setting, set_variable, unset_command_env,
+    unset_setting, unset_variable, update_clear_env_by_alias, update_command_by_alias,
+    update_confirm_by_alias, update_cwd_by_alias, update_info_by_alias,
+    update_interactive_by_alias, update_retries_by_alias, update_retry_delay_by_alias,
+    update_schedule_last_run, update_script_by_alias, update_service_by_alias,
+    update_shell_by_alias, update_template_by_alias, update_timeout_by_alias,
+    update_workflow_by_alias, Command, CommandEnv, PlaceholderSpec,
 };
+use regex::Regex;
 use rusqlite::{Connection, Result};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+mod config;
+mod cron;
+mod editor;
+mod exit_code;
+mod functions;
+mod parameterize;
+mod placeholder;
+mod prompt;
 mod shell_executor;
+mod template;
+mod usage;
+mod watch;
+mod workflow;
 use cli_table::{Cell, CellStruct, Style, Table, TableDisplay};
-use colored::Colorize;
-use shell_executor::replace_placeholder;
+use colored::{Color, ColoredString, Colorize};
+use placeholder::{
+    ambiguous_braces, placeholders, render_command, render_value, resolve_placeholders, Dialect,
+    Placeholder, Resolved, Source,
+};
+use shell_executor::Interactive;
+
+#[cfg(test)]
+mod cron_test;
+#[cfg(test)]
+mod editor_test;
+#[cfg(test)]
+mod main_test;
+#[cfg(test)]
+mod parameterize_test;
+#[cfg(test)]
+mod placeholder_test;
+#[cfg(test)]
+mod template_test;
+#[cfg(test)]
+mod watch_test;
+#[cfg(test)]
+mod workflow_test;
+
+pub fn print_brace_warnings(command: &str) {
+    for warning in ambiguous_braces(command) {
+        eprintln!("{}", format!("Warning: {}", warning).yellow());
+    }
+}
+
+// Looks a placeholder up in the variable store, service scope first. Only a
+// missing variable counts as not set; other database errors are returned.
+fn lookup_variable(
+    conn: &Connection,
+    name: &str,
+    service: &str,
+) -> Result<Option<(String, Source)>> {
+    let scopes = [
+        (Some(service), Source::ServiceVariable),
+        (None, Source::GlobalVariable),
+    ];
+    for (scope, source) in scopes {
+        match get_variable(conn, name, scope) {
+            Ok(value) => return Ok(Some((value, source))),
+            Err(rusqlite::Error::QueryReturnedNoRows) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(None)
+}
+
+// Offers the output of the placeholder's generator, if it has one, as choices.
+fn lookup_generator(name: &str, specs: &[PlaceholderSpec]) -> Option<(String, Source)> {
+    let spec = specs.iter().find(|spec| spec.name == name)?;
+    let generator = spec.generator.as_ref()?;
+    let column = spec.generator_column.map(|column| column as usize);
+
+    match shell_executor::generate_choices(generator, column)
+        .and_then(|choices| prompt::choose(name, &choices))
+    {
+        Ok(value) => Some((value, Source::Generator)),
+        Err(e) => {
+            eprintln!("{}", format!("{}", e).red());
+            None
+        }
+    }
+}
+
+// Offers values passed in earlier runs when the placeholder has nothing else to fall back on.
+fn lookup_history(
+    conn: &Connection,
+    command_id: i64,
+    placeholder: &Placeholder,
+) -> Option<(String, Source)> {
+    if placeholder.default.is_some() || !prompt::can_prompt() {
+        return None;
+    }
+
+    let history = retrieve_placeholder_history(conn, command_id, Some(&placeholder.name)).ok()?;
+    if history.is_empty() {
+        return None;
+    }
+
+    let choices: Vec<String> = history.into_iter().map(|h| h.value).collect();
+    match prompt::choose(&placeholder.name, &choices) {
+        Ok(value) => Some((value, Source::History)),
+        Err(e) => {
+            eprintln!("{}", format!("{}", e).red());
+            None
+        }
+    }
+}
+
+// Resolves placeholders that were not given on the command line.
+fn lookup_value(
+    conn: &Connection,
+    placeholder: &Placeholder,
+    command: &Command,
+    dotenv: &HashMap<String, String>,
+    specs: &[PlaceholderSpec],
+) -> std::result::Result<Option<(String, Source)>, Box<dyn Error>> {
+    let name = placeholder.name.as_str();
+    if let Some(key) = name.strip_prefix("env.") {
+        return Ok(env::var(key)
+            .ok()
+            .map(|value| (value, Source::Environment))
+            .or_else(|| dotenv.get(key).map(|value| (value.clone(), Source::DotEnv))));
+    }
+
+    Ok(lookup_variable(conn, name, &command.service)?
+        .or_else(|| {
+            dotenv
+                .get(name)
+                .map(|value| (value.clone(), Source::DotEnv))
+        })
+        .or_else(|| lookup_generator(name, specs))
+        .or_else(|| lookup_history(conn, command.id, placeholder)))
+}
+
+// The interpreter a command runs with: its own, or the default shell setting.
+fn interpreter(conn: &Connection, command: &Command) -> Option<String> {
+    command
+        .shell
+        .clone()
+        .or_else(|| get_setting(conn, config::SHELL).ok())
+}
+
+// Resolves the values of a saved command and renders the line to execute.
+struct ResolvedCommand {
+    resolved: Vec<Resolved>,
+    command: String,
+    env: Vec<(String, String)>,
+}
+
+fn resolve_command(
+    conn: &Connection,
+    command: &Command,
+    args: Vec<String>,
+    named: &[(String, String)],
+    cwd: Option<&Path>,
+) -> std::result::Result<ResolvedCommand, Box<dyn Error>> {
+    let dotenv = load_dotenv();
+    // Generator output is only offered as choices, so without anyone to choose
+    // from them, such as for scheduled runs, the generators are not run at all
+    let specs = match prompt::can_prompt() {
+        true => retrieve_placeholder_specs(conn, command.id)?,
+        false => vec![],
+    };
+    // Values are quoted for the language the command is written in
+    let interpreter = interpreter(conn, command);
+    let dialect = match command.script {
+        true => Dialect::of(shell_executor::shebang(&command.command).or(interpreter.as_deref())),
+        false => Dialect::of(interpreter.as_deref()),
+    };
+
+    let (mut resolved, rendered) = if command.template {
+        // Template variables are optional, so only look them up without prompting
+        let resolved = template::resolve_template_values(&command.command, args, named, |name| {
+            Ok(lookup_variable(conn, name, &command.service)?.or_else(|| {
+                dotenv
+                    .get(name)
+                    .map(|value| (value.clone(), Source::DotEnv))
+            }))
+        })?;
+        let rendered = template::render_template(&command.command, &resolved, dialect)?;
+        (resolved, rendered)
+    } else {
+        let resolved = resolve_placeholders(&command.command, args, named, cwd, |placeholder| {
+            lookup_value(conn, placeholder, command, &dotenv, &specs)
+        })?;
+        let rendered = render_command(&command.command, &resolved, dialect)?;
+        (resolved, rendered)
+    };
+    let env = resolve_env(conn, command, named, &mut resolved, &dotenv, &specs, cwd)?;
+
+    Ok(ResolvedCommand {
+        resolved,
+        command: rendered,
+        env,
+    })
+}
+
+// Fills in placeholders in the values of the command's environment variables,
+// reusing the values already resolved for the command itself and adding the new ones.
+fn resolve_env(
+    conn: &Connection,
+    command: &Command,
+    named: &[(String, String)],
+    resolved: &mut Vec<Resolved>,
+    dotenv: &HashMap<String, String>,
+    specs: &[PlaceholderSpec],
+    cwd: Option<&Path>,
+) -> std::result::Result<Vec<(String, String)>, Box<dyn Error>> {
+    let mut known = named.to_vec();
+    known.extend(resolved.iter().map(|r| (r.name.clone(), r.value())));
+
+    let mut env = vec![];
+    for variable in retrieve_command_env(conn, command.id)? {
+        let values = resolve_placeholders(&variable.value, vec![], &known, cwd, |placeholder| {
+            lookup_value(conn, placeholder, command, dotenv, specs)
+        })?;
+        env.push((variable.name, render_value(&variable.value, &values)));
+
+        for value in values {
+            if !resolved.iter().any(|r| r.name == value.name) {
+                known.push((value.name.clone(), value.value()));
+                resolved.push(value);
+            }
+        }
+    }
+    Ok(env)
+}
+
+// Rejects environment variable names a shell could not export.
+fn check_env_names(set: &[(String, String)]) -> std::result::Result<(), Box<dyn Error>> {
+    let valid_name = Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$").unwrap();
+    match set.iter().find(|(name, _)| !valid_name.is_match(name)) {
+        Some((name, _)) => Err(format!("Invalid environment variable name: {}", name).into()),
+        None => Ok(()),
+    }
+}
+
+// Sets and removes environment variables of the command saved under `alias`.
+fn save_command_env(
+    conn: &Connection,
+    alias: &str,
+    set: &[(String, String)],
+    unset: &[String],
+) -> std::result::Result<(), Box<dyn Error>> {
+    if set.is_empty() && unset.is_empty() {
+        return Ok(());
+    }
+    check_env_names(set)?;
+    let command = retrieve_command_by_alias(conn, alias)?;
+    for (name, value) in set {
+        set_command_env(conn, command.id, name, value)?;
+    }
+    for name in unset {
+        if unset_command_env(conn, command.id, name)? == 0 {
+            println!(
+                "{}",
+                format!("{} has no environment variable {}", alias, name).yellow()
+            );
+        }
+    }
+    Ok(())
+}
+
+// Saves the timeout and retry settings given to add/update; a zero timeout
+// removes the timeout.
+fn save_retry_settings(
+    conn: &Connection,
+    alias: &str,
+    timeout: Option<Duration>,
+    retries: Option<u32>,
+    retry_delay: Option<Duration>,
+) -> Result<()> {
+    if let Some(timeout) = timeout {
+        let timeout = (!timeout.is_zero()).then_some(timeout.as_millis() as i64);
+        update_timeout_by_alias(conn, alias, timeout)?;
+    }
+    if let Some(retries) = retries {
+        update_retries_by_alias(conn, alias, retries as i64)?;
+    }
+    if let Some(retry_delay) = retry_delay {
+        update_retry_delay_by_alias(conn, alias, Some(retry_delay.as_millis() as i64))?;
+    }
+    Ok(())
+}
+
+// Saves a new command together with the settings given to add, in one
+// transaction so a failed write leaves nothing behind.
+fn save_added_command(
+    conn: &Connection,
+    add: &cli::AddCommand,
+    command_text: &str,
+    confirm: bool,
+    interactive: bool,
+) -> Result<Command> {
+    let tx = conn.unchecked_transaction()?;
+    let alias = add.alias.as_str();
+    insert_command(
+        &tx,
+        command_text,
+        alias,
+        add.info.as_deref().unwrap_or_default(),
+        &add.service,
+    )?;
+    let command = retrieve_command_by_alias(&tx, alias)?;
+    if add.edit {
+        update_script_by_alias(&tx, alias, true)?;
+    }
+    if add.template {
+        update_template_by_alias(&tx, alias, true)?;
+    }
+    if add.workflow {
+        update_workflow_by_alias(&tx, alias, true)?;
+    }
+    if confirm {
+        update_confirm_by_alias(&tx, alias, true)?;
+    }
+    if add.clear_env {
+        update_clear_env_by_alias(&tx, alias, true)?;
+    }
+    if interactive {
+        update_interactive_by_alias(&tx, alias, true)?;
+    }
+    save_retry_settings(&tx, alias, add.timeout, Some(add.retries), add.retry_delay)?;
+    for (name, value) in &add.env {
+        set_command_env(&tx, command.id, name, value)?;
+    }
+    if let Some(ref cwd) = add.cwd {
+        update_cwd_by_alias(&tx, alias, Some(cwd))?;
+    }
+    if let Some(ref shell) = add.shell {
+        update_shell_by_alias(&tx, alias, Some(shell))?;
+    }
+    set_placeholder_specs(&tx, command.id, &add.generator, &add.column, &add.describe)?;
+    tx.commit()?;
+    Ok(command)
+}
+
+// Saves the changes given to update in one transaction, so a failed write
+// leaves the command as it was. Returns what changed, to print once saved.
+fn save_updated_command(
+    conn: &Connection,
+    update: &cli::UpdateCommand,
+    new_alias: Option<&str>,
+) -> std::result::Result<Vec<String>, Box<dyn Error>> {
+    let tx = conn.unchecked_transaction()?;
+    let alias = update.alias.as_str();
+    let mode = |enabled: bool| if enabled { "enabled" } else { "disabled" };
+    let mut saved = vec![];
+
+    if let Some(workflow) = update.workflow {
+        update_workflow_by_alias(&tx, alias, workflow)?;
+        saved.push(format!("Workflow mode {} for {}", mode(workflow), alias));
+    }
+    if let Some(template) = update.template {
+        update_template_by_alias(&tx, alias, template)?;
+        saved.push(format!("Template mode {} for {}", mode(template), alias));
+    }
+    if let Some(ref shell) = update.shell {
+        let shell = Some(shell.as_str()).filter(|shell| !shell.is_empty());
+        update_shell_by_alias(&tx, alias, shell)?;
+        saved.push(format!(
+            "Interpreter of {} set to {}",
+            alias,
+            shell.unwrap_or("the default shell")
+        ));
+    }
+    if !update.env.is_empty() || !update.unset_env.is_empty() {
+        save_command_env(&tx, alias, &update.env, &update.unset_env)?;
+        saved.push("Environment saved successfully!".to_string());
+    }
+    if let Some(confirm) = update.confirm {
+        update_confirm_by_alias(&tx, alias, confirm)?;
+        saved.push(format!("Confirmation {} for {}", mode(confirm), alias));
+    }
+    if update.timeout.is_some() || update.retries.is_some() || update.retry_delay.is_some() {
+        save_retry_settings(
+            &tx,
+            alias,
+            update.timeout,
+            update.retries,
+            update.retry_delay,
+        )?;
+        saved.push("Timeout and retries saved successfully!".to_string());
+    }
+    if let Some(clear_env) = update.clear_env {
+        update_clear_env_by_alias(&tx, alias, clear_env)?;
+        let inherits = if clear_env {
+            "runs without"
+        } else {
+            "inherits"
+        };
+        saved.push(format!("{} {} the environment", alias, inherits));
+    }
+    if let Some(ref cwd) = update.cwd {
+        // An empty directory runs the command where smriti is started
+        let cwd = Some(cwd.as_str()).filter(|cwd| !cwd.is_empty());
+        update_cwd_by_alias(&tx, alias, cwd)?;
+        saved.push(format!(
+            "Directory of {} set to {}",
+            alias,
+            cwd.unwrap_or("the current directory")
+        ));
+    }
+    if let Some(interactive) = update.interactive {
+        update_interactive_by_alias(&tx, alias, interactive)?;
+        saved.push(format!(
+            "Interactive mode {} for {}",
+            mode(interactive),
+            alias
+        ));
+    }
+    if let Some(ref command) = update.command {
+        if update.edit {
+            update_script_by_alias(&tx, alias, true)?;
+        }
+        update_command_by_alias(&tx, alias, command)?;
+    }
+    if let Some(ref service) = update.service {
+        update_service_by_alias(&tx, alias, service)?;
+    }
+    if let Some(ref info) = update.info {
+        update_info_by_alias(&tx, alias, info)?;
+    }
+    if !update.generator.is_empty() || !update.column.is_empty() || !update.describe.is_empty() {
+        let command = retrieve_command_by_alias(&tx, alias)?;
+        set_placeholder_specs(
+            &tx,
+            command.id,
+            &update.generator,
+            &update.column,
+            &update.describe,
+        )?;
+        saved.push("Placeholder settings saved successfully!".to_string());
+    }
+    if let Some(new_alias) = new_alias {
+        rename_alias(&tx, alias, new_alias)?;
+        saved.push(format!("Renamed {} to {}", alias, new_alias));
+    }
+    tx.commit()?;
+    Ok(saved)
+}
+
+// Checks the template syntax of a command being saved under `alias`, returning
+// whether the command is in template mode.
+fn check_saved_template(
+    conn: &Connection,
+    alias: &str,
+    command: Option<&str>,
+    template: Option<bool>,
+) -> std::result::Result<bool, Box<dyn Error>> {
+    let existing = retrieve_command_by_alias(conn, alias).ok();
+    let template = template
+        .or(existing.as_ref().map(|c| c.template))
+        .unwrap_or(false);
+
+    if template {
+        let source = command
+            .map(|c| c.to_string())
+            .or(existing.map(|c| c.command));
+        if let Some(source) = source {
+            template::check_template(&source)?;
+        }
+    }
+    Ok(template)
+}
+
+// Checks the steps of a command being saved under `alias` as a workflow:
+// every step must be a saved alias and no workflow may end up running itself.
+// Returns whether the command is a workflow.
+fn check_saved_workflow(
+    conn: &Connection,
+    alias: &str,
+    command: Option<&str>,
+    workflow: Option<bool>,
+) -> std::result::Result<bool, Box<dyn Error>> {
+    let existing = retrieve_command_by_alias(conn, alias).ok();
+    let workflow = workflow
+        .or(existing.as_ref().map(|c| c.workflow))
+        .unwrap_or(false);
+    let body = command
+        .map(|c| c.to_string())
+        .or(existing.map(|c| c.command));
+    let (true, Some(body)) = (workflow, body) else {
+        return Ok(workflow);
+    };
+
+    let steps = workflow::parse_steps(&body)?;
+    for step in &steps {
+        if step.alias != alias && retrieve_command_by_alias(conn, &step.alias).is_err() {
+            return Err(format!("No command is saved under the step alias {}", step.alias).into());
+        }
+    }
+    let cycle = workflow::find_cycle(alias, |name| {
+        if name == alias {
+            return Some(steps.iter().map(|step| step.alias.clone()).collect());
+        }
+        workflow_steps(conn, name)
+    });
+    if let Some(cycle) = cycle {
+        return Err(format!("Workflow runs itself: {}", cycle.join(" -> ")).into());
+    }
+    Ok(true)
+}
+
+// Opens the editor for `add --edit` and `update --edit`, and checks that what
+// was saved has an alias, a service and a script.
+fn edit_script(file: editor::ScriptFile) -> Result<editor::ScriptFile, Box<dyn Error>> {
+    let edited = editor::parse_script_file(&editor::edit(&editor::script_file(&file))?);
+    if edited.script.trim().is_empty() {
+        return Err("The script is empty, nothing was saved".into());
+    }
+    if edited.alias.is_empty() || edited.alias.contains(char::is_whitespace) {
+        return Err("The alias in the header must be a single word".into());
+    }
+    if edited.service.is_empty() {
+        return Err("The service in the header is empty".into());
+    }
+    Ok(edited)
+}
+
+// The step aliases of a saved workflow, or None for other commands.
+fn workflow_steps(conn: &Connection, alias: &str) -> Option<Vec<String>> {
+    let command = retrieve_command_by_alias(conn, alias)
+        .ok()
+        .filter(|command| command.workflow)?;
+    let steps = workflow::parse_steps(&command.command).ok()?;
+    Some(steps.into_iter().map(|step| step.alias).collect())
+}
+
+// Proposes placeholders for the literals in a concrete command, asking for
+// their names when there is a terminal.
+fn parameterize_command(command: &str) -> String {
+    let literals = parameterize::detect_literals(command);
+    if literals.is_empty() {
+        println!("No IP addresses, ports, URLs or paths found to parameterize");
+        return command.to_string();
+    }
+
+    let valid_name = Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$").unwrap();
+    let interactive = prompt::can_prompt();
+    if interactive {
+        eprintln!(
+            "{}",
+            "Name each placeholder, or enter - to keep the value as it is".cyan()
+        );
+    }
+
+    parameterize::parameterize(command, &literals, |literal| {
+        if !interactive {
+            return Some(literal.name.clone());
+        }
+        loop {
+            let question = format!("Placeholder for {:?}", literal.value);
+            match prompt::ask(&question, &literal.name) {
+                Ok(name) if name == "-" => return None,
+                Ok(name) if valid_name.is_match(&name) => return Some(name),
+                Ok(name) => eprintln!("{}", format!("Invalid placeholder name: {}", name).red()),
+                Err(_) => return Some(literal.name.clone()),
+            }
+        }
+    })
+}
+
+// Remembers the values the user supplied so later runs can suggest them.
+fn record_history(conn: &Connection, command_id: i64, resolved: &[Resolved]) {
+    for placeholder in resolved {
+        if matches!(
+            placeholder.source,
+            Source::Argument | Source::Generator | Source::History
+        ) {
+            for value in &placeholder.values {
+                let _ = record_placeholder_value(conn, command_id, &placeholder.name, value);
+            }
+        }
+    }
+}
+
+// Prints suggestions for shell completion: aliases, or values for the placeholder at `position`.
+fn print_completions(conn: &Connection, alias: Option<&str>, position: usize) {
+    let Some(alias) = alias else {
+        if let Ok(aliases) = display_by_type(conn, "alias") {
+            aliases.iter().for_each(|alias| println!("{}", alias));
+        }
+        return;
+    };
+
+    let Ok(command) = retrieve_command_by_alias(conn, alias) else {
+        return;
+    };
+    let positional: Vec<Placeholder> = placeholders(&command.command)
+        .into_iter()
+        .filter(|p| p.is_positional())
+        .collect();
+    let placeholder = match positional.get(position) {
+        Some(placeholder) => placeholder,
+        None => match positional.last() {
+            Some(last) if last.variadic.is_some() => last,
+            _ => return,
+        },
+    };
+
+    if let Ok(history) = retrieve_placeholder_history(conn, command.id, Some(&placeholder.name)) {
+        history.iter().for_each(|h| println!("{}", h.value));
+    }
+}
+
+// Warns about generators, columns and descriptions given for names that are
+// not placeholders of `command`.
+fn check_placeholder_specs(
+    command: &str,
+    alias: &str,
+    generators: &[(String, String)],
+    columns: &[(String, usize)],
+    descriptions: &[(String, String)],
+) {
+    let names: Vec<String> = placeholders(command).into_iter().map(|p| p.name).collect();
+
+    let configured = generators
+        .iter()
+        .map(|(name, _)| name)
+        .chain(columns.iter().map(|(name, _)| name))
+        .chain(descriptions.iter().map(|(name, _)| name));
+    for name in configured {
+        if !names.contains(name) {
+            eprintln!(
+                "{}",
+                format!("Warning: {{{}}} is not a placeholder of {}", name, alias).yellow()
+            );
+        }
+    }
+}
+
+// Writes placeholder settings for the command with `command_id`.
+fn set_placeholder_specs(
+    conn: &Connection,
+    command_id: i64,
+    generators: &[(String, String)],
+    columns: &[(String, usize)],
+    descriptions: &[(String, String)],
+) -> Result<()> {
+    for (name, generator) in generators {
+        set_placeholder_generator(conn, command_id, name, generator)?;
+    }
+    for (name, column) in columns {
+        set_placeholder_column(conn, command_id, name, *column as i64)?;
+    }
+    for (name, description) in descriptions {
+        set_placeholder_description(conn, command_id, name, description)?;
+    }
+    Ok(())
+}
+
+// Prints the generated help of a saved command, or of `run` itself without an alias.
+fn print_usage(conn: &Connection, alias: Option<&str>) {
+    let Some(alias) = alias else {
+        let mut cli = SmritiCli::command();
+        cli.build();
+        if let Some(run) = cli.find_subcommand_mut("run") {
+            let _ = run.print_help();
+        }
+        return;
+    };
+
+    match retrieve_command_by_alias(conn, alias) {
+        Ok(command) => {
+            let specs = retrieve_placeholder_specs(conn, command.id).unwrap_or_default();
+            print!("{}", usage::usage_text(&command, &specs));
+        }
+        Err(e) => eprintln!(
+            "{}",
+            format!(
+                "Error retrieving command: {} \nNo command associated with alias: {:?}",
+                e, alias
+            )
+            .red()
+        ),
+    }
+}
+
+// Wait before the first retry of commands that do not set their own.
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// How often a failed command is run again.
+struct RetryPolicy {
+    retries: u32,
+    /// Wait before the first retry, doubled before each following one.
+    delay: Duration,
+}
+
+impl RetryPolicy {
+    fn of(command: &Command) -> RetryPolicy {
+        RetryPolicy {
+            retries: command.retries as u32,
+            delay: command
+                .retry_delay_ms
+                .map(|ms| Duration::from_millis(ms as u64))
+                .unwrap_or(DEFAULT_RETRY_DELAY),
+        }
+    }
+}
+
+// The exit code of a failed database call: no command under the alias, a
+// value breaking a constraint such as an alias already taken, or anything else.
+fn database_exit_code(err: &rusqlite::Error) -> i32 {
+    match err {
+        rusqlite::Error::QueryReturnedNoRows => exit_code::UNKNOWN_ALIAS,
+        rusqlite::Error::SqliteFailure(e, _)
+            if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            exit_code::USAGE
+        }
+        _ => exit_code::DATABASE,
+    }
+}
+
+// The exit code of an error that came from the database or from what was given.
+fn error_exit_code(err: &(dyn Error + 'static)) -> i32 {
+    match err.downcast_ref::<rusqlite::Error>() {
+        Some(err) => database_exit_code(err),
+        None => exit_code::USAGE,
+    }
+}
+
+// Runs a saved command and returns the exit code smriti should exit with.
+// `workflows` holds the workflows being run, to catch them running themselves.
+fn run_alias(conn: &Connection, arg: RunCommand, workflows: &mut Vec<String>) -> i32 {
+    let command = match retrieve_command_by_alias(conn, arg.alias.as_deref().unwrap_or_default()) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("{}", format!("Error retrieving command: {}", err).red());
+            return database_exit_code(&err);
+        }
+    };
+    if !arg.watch.is_empty() && (command.workflow || command.interactive) {
+        let kind = if command.workflow {
+            "a workflow"
+        } else {
+            "interactive"
+        };
+        eprintln!(
+            "{}",
+            format!("{} is {}, it cannot be watched", command.alias, kind).red()
+        );
+        return exit_code::USAGE;
+    }
+    if command.workflow {
+        return run_workflow(conn, &command, arg, workflows);
+    }
+
+    let run = match prepare_run(conn, command, &arg) {
+        Ok(run) => run,
+        Err(code) => return code,
+    };
+
+    if arg.dry_run {
+        print_dry_run(&run.line, &run.options, &run.retry);
+        return 0;
+    }
+
+    if run.command.confirm && !arg.yes && !confirmed(&run.command, &run.line) {
+        return exit_code::NOT_CONFIRMED;
+    }
+
+    if arg.print {
+        println!("{}", run.line);
+        return 0;
+    }
+
+    if !arg.watch.is_empty() {
+        return run_watched(conn, run, &arg);
+    }
+    execute_with_retries(conn, &run, records_attempts(&run, &arg))
+}
+
+// How long files must stay unchanged before a watched command runs again.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(200);
+
+// Runs a command again whenever a watched file changes, stopping the run that
+// is still going first. Returns once a run is interrupted with Ctrl-C.
+fn run_watched(conn: &Connection, mut run: PreparedRun, arg: &RunCommand) -> i32 {
+    let root = env::current_dir().unwrap_or_default();
+    let mut watcher = match watch::Watcher::new(&root, &arg.watch) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("{}", format!("Error watching files: {}", e).red());
+            return exit_code::USAGE;
+        }
+    };
+    let cancel = Arc::new(AtomicBool::new(false));
+    run.options.cancel = Some(cancel.clone());
+    let watching = format!(
+        "Watching {} for changes to {}, Ctrl-C to stop",
+        root.display(),
+        arg.watch.join(", ")
+    );
+
+    let mut change: Option<PathBuf> = None;
+    let mut count = 0;
+    loop {
+        count += 1;
+        if arg.clear {
+            print!("\x1b[2J\x1b[H");
+            println!("{}", watching.cyan());
+        } else if count == 1 {
+            println!("{}", watching.cyan());
+        }
+        if let Some(ref path) = change {
+            let heading = format!("── {} changed, run {} ──", path.display(), count);
+            println!(
+                "{}{}",
+                if arg.clear { "" } else { "\n" },
+                heading.cyan().bold()
+            );
+        }
+
+        cancel.store(false, Ordering::SeqCst);
+        let started = Instant::now();
+        let mut attempts = vec![];
+        let (code, changed) = thread::scope(|scope| {
+            let worker = scope.spawn(|| {
+                run_attempts(&run, |attempt, result| {
+                    attempts.push((
+                        attempt,
+                        result.duration,
+                        result.exit_code(),
+                        result.output.clone(),
+                    ))
+                })
+            });
+            let mut changed = Ok(None);
+            while !worker.is_finished() {
+                changed = watcher.changed(Some(Duration::from_millis(100)));
+                if !matches!(changed, Ok(None)) {
+                    cancel.store(true, Ordering::SeqCst);
+                    break;
+                }
+            }
+            (worker.join().unwrap(), changed)
+        });
+
+        // Runs are stored here, as the connection stays on this thread
+        if records_attempts(&run, arg) {
+            record_attempts(conn, run.command.id, &run.line, attempts);
+        }
+
+        let changed = match changed {
+            Ok(Some(path)) => {
+                println!(
+                    "{}",
+                    format!("{} changed, stopping the run", path.display()).yellow()
+                );
+                Ok(Some(path))
+            }
+            Ok(None) if code == 128 + libc::SIGINT => return code,
+            Ok(None) => {
+                let status = match code {
+                    0 => "ok".green(),
+                    code => format!("failed with {}", code).red(),
+                };
+                let duration = format!("{:.1}s", started.elapsed().as_secs_f64());
+                println!(
+                    "{} {} {}",
+                    "──".dimmed(),
+                    status,
+                    format!("in {}, waiting for changes ──", duration).dimmed()
+                );
+                watcher.changed(None)
+            }
+            Err(e) => Err(e),
+        };
+        change = match changed.and_then(|path| watcher.settle(WATCH_DEBOUNCE).map(|_| path)) {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("{}", format!("Error watching files: {}", e).red());
+                return exit_code::FAILURE;
+            }
+        };
+    }
+}
+
+// The directory a command runs in: --cwd, else its own, else the current one.
+fn working_directory(
+    command: &Command,
+    arg: &RunCommand,
+) -> std::result::Result<Option<PathBuf>, Box<dyn Error>> {
+    arg.cwd
+        .as_ref()
+        .or(command.cwd.as_ref())
+        .map(|cwd| shell_executor::expand_directory(cwd))
+        .transpose()
+}
+
+// A saved command resolved and ready to execute.
+struct PreparedRun {
+    command: Command,
+    /// The command line with the placeholders filled in.
+    line: String,
+    options: shell_executor::ExecOptions,
+    retry: RetryPolicy,
+}
+
+// Resolves a saved command and works out how to execute it, applying the
+// flags given to `run`. Returns the exit code to give up with on errors.
+fn prepare_run(
+    conn: &Connection,
+    command: Command,
+    arg: &RunCommand,
+) -> std::result::Result<PreparedRun, i32> {
+    let cwd = match working_directory(&command, arg) {
+        Ok(cwd) => cwd,
+        Err(e) => {
+            eprintln!("{}", format!("{}", e).red());
+            return Err(exit_code::USAGE);
+        }
+    };
+    let ResolvedCommand {
+        resolved,
+        command: line,
+        mut env,
+    } = match resolve_command(
+        conn,
+        &command,
+        arg.variables.clone(),
+        &arg.set,
+        cwd.as_deref(),
+    ) {
+        Ok(result) => result,
+        Err(err) => {
+            eprintln!("{}", format!("Error retrieving command: {}", err).red());
+            return Err(error_exit_code(&*err));
+        }
+    };
+
+    if arg.show_resolved && !resolved.is_empty() {
+        print_resolved(&resolved);
+    }
+    // Nothing runs in dry-run and print mode, so there is nothing to remember
+    if !arg.no_history && !arg.dry_run && !arg.print {
+        record_history(conn, command.id, &resolved);
+    }
+
+    // Interactive programs need the terminal, so their output cannot be captured
+    let capture = arg.capture && !command.interactive;
+    if arg.capture && command.interactive {
+        eprintln!(
+            "{}",
+            format!(
+                "{} is interactive, its output is not captured",
+                command.alias
+            )
+            .yellow()
+        );
+    }
+
+    // --env overrides the command's own variables
+    for (name, value) in &arg.env {
+        env.retain(|(existing, _)| existing != name);
+        env.push((name.clone(), value.clone()));
+    }
+
+    let mut timeout = arg
+        .timeout
+        .or(command
+            .timeout_ms
+            .map(|ms| Duration::from_millis(ms as u64)))
+        .filter(|timeout| !timeout.is_zero());
+    // Stopping a command needs its own process group, which takes it off the terminal
+    if timeout.is_some() && command.interactive {
+        eprintln!(
+            "{}",
+            format!(
+                "{} is interactive, it runs without a timeout",
+                command.alias
+            )
+            .yellow()
+        );
+        timeout = None;
+    }
+    let mut retry = RetryPolicy::of(&command);
+    retry.retries = arg.retries.unwrap_or(retry.retries);
+    retry.delay = arg.retry_delay.unwrap_or(retry.delay);
+
+    let options = shell_executor::ExecOptions {
+        capture,
+        cwd,
+        env,
+        clear_env: arg.clear_env || command.clear_env,
+        interpreter: interpreter(conn, &command),
+        timeout,
+        prefix: None,
+        script: command.script,
+        cancel: None,
+    };
+
+    Ok(PreparedRun {
+        command,
+        line,
+        options,
+        retry,
+    })
+}
+
+// Shows a command that needs confirmation and asks for it.
+fn confirmed(command: &Command, line: &str) -> bool {
+    eprintln!(
+        "{}\n  {}",
+        format!("{} needs confirmation before it runs:", command.alias).yellow(),
+        line
+    );
+    match prompt::confirm(&command.alias) {
+        Ok(true) => true,
+        Ok(false) => {
+            eprintln!("{}", "Not confirmed, nothing was run".red());
+            false
+        }
+        Err(e) => {
+            eprintln!("{}", format!("{}", e).red());
+            false
+        }
+    }
+}
+
+// How a workflow step ended, for the summary.
+enum StepStatus {
+    Succeeded,
+    Failed(i32),
+    /// Failed, but the step is allowed to.
+    Continued(i32),
+    /// Not run, for the given reason.
+    Skipped(&'static str),
+}
+
+// Runs the steps of a workflow in order, each like `smriti run` would with the
+// same flags, and prints a summary. Values of the workflow's placeholders are
+// passed to every step. Returns the exit code of the step that stopped it.
+fn run_workflow(
+    conn: &Connection,
+    workflow: &Command,
+    arg: RunCommand,
+    workflows: &mut Vec<String>,
+) -> i32 {
+    if workflows.contains(&workflow.alias) {
+        workflows.push(workflow.alias.clone());
+        eprintln!(
+            "{}",
+            format!("Workflow runs itself: {}", workflows.join(" -> ")).red()
+        );
+        return exit_code::USAGE;
+    }
+    let steps = match workflow::parse_steps(&workflow.command) {
+        Ok(steps) => steps,
+        Err(e) => {
+            eprintln!("{}", format!("Error in workflow: {}", e).red());
+            return exit_code::USAGE;
+        }
+    };
+    let resolved = working_directory(workflow, &arg).and_then(|cwd| {
+        resolve_command(
+            conn,
+            workflow,
+            arg.variables.clone(),
+            &arg.set,
+            cwd.as_deref(),
+        )
+    });
+    let resolved = match resolved {
+        Ok(result) => result.resolved,
+        Err(err) => {
+            eprintln!("{}", format!("Error retrieving command: {}", err).red());
+            return error_exit_code(&*err);
+        }
+    };
+
+    if arg.show_resolved && !resolved.is_empty() {
+        print_resolved(&resolved);
+    }
+    if !arg.no_history && !arg.dry_run && !arg.print {
+        record_history(conn, workflow.id, &resolved);
+    }
+    if workflow.confirm
+        && !arg.yes
+        && !arg.dry_run
+        && !confirmed(workflow, &render_value(&workflow.command, &resolved))
+    {
+        return exit_code::NOT_CONFIRMED;
+    }
+
+    let mut named: Vec<(String, String)> = arg
+        .set
+        .iter()
+        .filter(|(name, _)| !resolved.iter().any(|r| r.name == *name))
+        .cloned()
+        .collect();
+    for value in resolved.iter().filter(|r| r.source != Source::Function) {
+        named.extend(value.values.iter().map(|v| (value.name.clone(), v.clone())));
+    }
+
+    workflows.push(workflow.alias.clone());
+    let mut results = vec![];
+    let mut code = 0;
+    for (index, step) in steps.iter().enumerate() {
+        if code != 0 {
+            results.push((step, StepStatus::Skipped("an earlier step failed"), None));
+            continue;
+        }
+        if !arg.print {
+            println!(
+                "{}",
+                format!("[{}/{}] {}", index + 1, steps.len(), step.alias)
+                    .cyan()
+                    .bold()
+            );
+        }
+
+        let started = Instant::now();
+        let step_arg = RunCommand {
+            alias: Some(step.alias.clone()),
+            variables: workflow::render_args(&step.args, &resolved),
+            set: named.clone(),
+            ..arg.clone()
+        };
+        let status = match run_alias(conn, step_arg, workflows) {
+            0 => StepStatus::Succeeded,
+            failed if step.continue_on_error => StepStatus::Continued(failed),
+            failed => {
+                code = failed;
+                StepStatus::Failed(failed)
+            }
+        };
+        results.push((step, status, Some(started.elapsed())));
+    }
+    workflows.pop();
+
+    if !arg.print {
+        let rows = results
+            .into_iter()
+            .enumerate()
+            .map(|(index, (step, status, duration))| {
+                let mut words = vec![step.alias.clone()];
+                words.extend(step.args.iter().cloned());
+                (
+                    vec![(index + 1).to_string(), words.join(" ")],
+                    status,
+                    duration,
+                )
+            })
+            .collect();
+        print_summary(&["Step", "Command"], rows);
+    }
+    code
+}
+
+// Colors telling apart the output of commands running side by side.
+const PREFIX_COLORS: [Color; 10] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Yellow,
+    Color::Blue,
+    Color::Green,
+    Color::BrightCyan,
+    Color::BrightMagenta,
+    Color::BrightYellow,
+    Color::BrightBlue,
+    Color::BrightGreen,
+];
+
+// Runs every command of a service in the order they were added, `--parallel`
+// at a time, with each line of output prefixed by the alias, and prints a
+// summary. Returns 0 when all of them succeeded, or else the exit code of the
+// first one that failed.
+fn run_service(conn: &Connection, service: &str, arg: RunCommand) -> i32 {
+    let commands = match retrieve_commands_by_service(conn, service) {
+        Ok(commands) if !commands.is_empty() => commands,
+        Ok(_) => {
+            eprintln!(
+                "{}",
+                format!("No commands found for the provided '{}' service.", service).red()
+            );
+            return exit_code::UNKNOWN_ALIAS;
+        }
+        Err(e) => {
+            eprintln!("{}", format!("Error retrieving commands: {}", e).red());
+            return exit_code::DATABASE;
+        }
+    };
+    let width = commands.iter().map(|c| c.alias.len()).max().unwrap_or(0);
+
+    // Everything is resolved and confirmed up front, as prompts cannot be
+    // answered once commands run side by side
+    let mut results = vec![];
+    let mut queue = vec![];
+    for (index, command) in commands.into_iter().enumerate() {
+        let alias = command.alias.clone();
+        let skipped = if command.workflow {
+            Some("workflow")
+        } else if command.interactive {
+            Some("interactive")
+        } else {
+            None
+        };
+        if let Some(reason) = skipped {
+            results.push((vec![alias], StepStatus::Skipped(reason), None));
+            continue;
+        }
+
+        let mut run = match prepare_run(conn, command, &arg) {
+            Ok(run) => run,
+            Err(code) => {
+                results.push((vec![alias], StepStatus::Failed(code), None));
+                continue;
+            }
+        };
+        if arg.dry_run {
+            print_dry_run(&run.line, &run.options, &run.retry);
+            println!();
+            continue;
+        }
+        if run.command.confirm && !arg.yes && !confirmed(&run.command, &run.line) {
+            results.push((vec![alias], StepStatus::Skipped("not confirmed"), None));
+            continue;
+        }
+        if arg.print {
+            println!("{}", run.line);
+            continue;
+        }
+
+        let color = PREFIX_COLORS[index % PREFIX_COLORS.len()];
+        run.options.prefix = Some(format!("{} | ", format!("{:<width$}", alias).color(color)));
+        queue.push((results.len(), run));
+        results.push((vec![alias], StepStatus::Skipped("not run"), None));
+    }
+    if arg.dry_run || arg.print {
+        return 0;
+    }
+
+    let queue = Mutex::new(queue.into_iter());
+    let finished = Mutex::new(vec![]);
+    thread::scope(|scope| {
+        for _ in 0..arg.parallel.unwrap_or(1) {
+            scope.spawn(|| loop {
+                let Some((index, run)) = queue.lock().unwrap().next() else {
+                    break;
+                };
+                let started = Instant::now();
+                let mut attempts = vec![];
+                let code = run_attempts(&run, |attempt, result| {
+                    attempts.push((
+                        attempt,
+                        result.duration,
+                        result.exit_code(),
+                        result.output.clone(),
+                    ))
+                });
+                finished
+                    .lock()
+                    .unwrap()
+                    .push((index, run, code, started.elapsed(), attempts));
+            });
+        }
+    });
+
+    // Runs are stored afterwards, as the connection stays on this thread
+    for (index, run, code, duration, attempts) in finished.into_inner().unwrap() {
+        if records_attempts(&run, &arg) {
+            record_attempts(conn, run.command.id, &run.line, attempts);
+        }
+        results[index].1 = match code {
+            0 => StepStatus::Succeeded,
+            code => StepStatus::Failed(code),
+        };
+        results[index].2 = Some(duration);
+    }
+
+    let code = results
+        .iter()
+        .find_map(|(_, status, _)| match status {
+            StepStatus::Failed(code) => Some(*code),
+            _ => None,
+        })
+        .unwrap_or(0);
+    print_summary(&["Alias"], results);
+    code
+}
+
+// One try of a command run on another thread: the attempt number, duration,
+// exit code and captured output, stored once back with the connection.
+type Attempt = (u32, Duration, Option<i32>, Option<String>);
+
+fn record_attempts(conn: &Connection, command_id: i64, line: &str, attempts: Vec<Attempt>) {
+    for (attempt, duration, exit_code, output) in attempts {
+        if let Err(e) = record_run(
+            conn,
+            command_id,
+            line,
+            duration.as_millis() as i64,
+            exit_code,
+            output.as_deref(),
+            attempt as i64,
+        ) {
+            eprintln!("{}", format!("Error saving run log: {}", e).red());
+        }
+    }
+}
+
+// Prints how each of several commands ended, after the cells given for each.
+fn print_summary(titles: &[&str], rows: Vec<(Vec<String>, StepStatus, Option<Duration>)>) {
+    let mut table = vec![];
+    for (cells, status, duration) in rows {
+        let status = match status {
+            StepStatus::Succeeded => "ok".green(),
+            StepStatus::Failed(code) => format!("failed with {}", code).red(),
+            StepStatus::Continued(code) => format!("failed with {}, continued", code).yellow(),
+            StepStatus::Skipped(reason) => format!("skipped ({})", reason).dimmed(),
+        };
+        let duration = duration
+            .map(|d| format!("{:.1}s", d.as_secs_f64()))
+            .unwrap_or_else(|| "-".to_string());
+        let mut row: Vec<CellStruct> = cells.into_iter().map(|cell| cell.cell()).collect();
+        row.push(status.cell());
+        row.push(duration.cell());
+        table.push(row);
+    }
+
+    let mut title: Vec<CellStruct> = titles
+        .iter()
+        .map(|title| title.cyan().cell().bold(true))
+        .collect();
+    title.push("Status".cyan().cell().bold(true));
+    title.push("Duration".cyan().cell().bold(true));
+    let table_display = table.table().title(title).display().unwrap();
+
+    println!("{}", table_display);
+}
+
+// Whether the attempts of a run are stored: when it is captured, and when it
+// may be retried, so every attempt shows up in `smriti logs` even without its output.
+fn records_attempts(run: &PreparedRun, arg: &RunCommand) -> bool {
+    arg.capture || run.retry.retries > 0
+}
+
+// Runs a prepared command, storing each attempt as a run when `record`.
+fn execute_with_retries(conn: &Connection, run: &PreparedRun, record: bool) -> i32 {
+    run_attempts(run, |attempt, result| {
+        if !record {
+            return;
+        }
+        if let Err(e) = record_run(
+            conn,
+            run.command.id,
+            &run.line,
+            result.duration.as_millis() as i64,
+            result.exit_code(),
+            result.output.as_deref(),
+            attempt as i64,
+        ) {
+            eprintln!("{}", format!("Error saving run log: {}", e).red());
+        }
+    })
+}
+
+// Runs a command until it succeeds or runs out of retries, and returns the
+// exit code of the last attempt. `finished` is called after every attempt.
+fn run_attempts<F>(run: &PreparedRun, mut finished: F) -> i32
+where
+    F: FnMut(u32, &shell_executor::RunResult),
+{
+    let prefix = run.options.prefix.as_deref().unwrap_or_default();
+    let attempts = run.retry.retries + 1;
+    let mut delay = run.retry.delay;
+    let mut attempt = 1;
+    loop {
+        let result = match shell_executor::execute_command(run.line.clone(), &run.options) {
+            Ok(result) => result,
+            Err(e) => {
+                eprintln!(
+                    "{}{}",
+                    prefix,
+                    format!("Error executing command: check variables passed {}", e).red()
+                );
+                return exit_code::CANNOT_EXECUTE;
+            }
+        };
+        finished(attempt, &result);
+
+        let code = result.exit_code().unwrap_or(exit_code::FAILURE);
+        // A command stopped with Ctrl-C, or that was cancelled, is not tried again
+        if result.status.success()
+            || attempt == attempts
+            || code == 128 + libc::SIGINT
+            || run.options.cancelled()
+        {
+            return code;
+        }
+        attempt += 1;
+        eprintln!(
+            "{}{}",
+            prefix,
+            format!(
+                "Retrying in {} (attempt {} of {})",
+                format_duration(delay),
+                attempt,
+                attempts
+            )
+            .yellow()
+        );
+        thread::sleep(delay);
+        delay = delay.saturating_mul(2);
+    }
+}
+
+// Shows what `run` would execute, and how, without executing it.
+fn print_dry_run(command: &str, options: &shell_executor::ExecOptions, retry: &RetryPolicy) {
+    let label = |name: &str| format!("{:<13}", format!("{}:", name)).cyan().bold();
+
+    let mut interpreter = options.interpreter.as_deref();
+    if options.script {
+        println!("{}\n{}", label("Script"), command.trim_end());
+        interpreter = shell_executor::shebang(command).or(interpreter);
+    } else {
+        println!("{}{}", label("Command"), command);
+    }
+    println!("{}{}", label("Interpreter"), interpreter.unwrap_or("sh"));
+    match options.cwd {
+        Some(ref cwd) => println!("{}{}", label("Directory"), cwd.display()),
+        None => println!("{}(current directory)", label("Directory")),
+    }
+    if options.clear_env {
+        println!("{}(cleared)", label("Environment"));
+    }
+    for (name, value) in &options.env {
+        println!(
+            "{}{}={}",
+            label("Environment"),
+            name,
+            placeholder::quote_value(value, placeholder::Quote::None)
+        );
+    }
+    if let Some(timeout) = options.timeout {
+        println!("{}{}", label("Timeout"), format_duration(timeout));
+    }
+    if retry.retries > 0 {
+        println!(
+            "{}{}, {} apart at first",
+            label("Retries"),
+            retry.retries,
+            format_duration(retry.delay)
+        );
+    }
+}
+
+// How a command runs, for the settings that differ from the defaults.
+fn run_settings(command: &Command, env: &[CommandEnv]) -> Vec<(&'static str, String)> {
+    let mut settings = vec![];
+    if let Some(ref cwd) = command.cwd {
+        settings.push(("Directory", cwd.clone()));
+    }
+    for variable in env {
+        settings.push((
+            "Environment",
+            format!("{}={}", variable.name, variable.value),
+        ));
+    }
+    if command.clear_env {
+        settings.push(("Clear environment", "yes".to_string()));
+    }
+    if command.confirm {
+        settings.push(("Confirm", "yes".to_string()));
+    }
+    if let Some(timeout) = command.timeout_ms {
+        let timeout = Duration::from_millis(timeout as u64);
+        settings.push(("Timeout", format_duration(timeout)));
+    }
+    if command.retries > 0 {
+        let retry = RetryPolicy::of(command);
+        settings.push((
+            "Retries",
+            format!(
+                "{}, {} apart at first",
+                retry.retries,
+                format_duration(retry.delay)
+            ),
+        ));
+    }
+    if let Some(ref shell) = command.shell {
+        settings.push(("Interpreter", shell.clone()));
+    }
+    if command.interactive {
+        settings.push(("Interactive", "yes".to_string()));
+    }
+    if command.template {
+        settings.push(("Template", "yes".to_string()));
+    }
+    if command.workflow {
+        settings.push(("Workflow", "yes".to_string()));
+    }
+    if command.script {
+        settings.push(("Script", "yes".to_string()));
+    }
+
+    settings
+}
+
+// Scheduled times are counted as missed up to this many, the ones before
+// are skipped without being counted.
+const MAX_MISSED_RUNS: usize = 100;
+
+// A scheduled time counts as on time when the scheduler gets to it within this many seconds.
+const SCHEDULE_GRACE: i64 = 60;
+
+// Local time of a unix time, the time zone cron expressions are in.
+fn local_time(timestamp: i64) -> Option<chrono::NaiveDateTime> {
+    chrono::Local
+        .timestamp_opt(timestamp, 0)
+        .earliest()
+        .map(|time| time.naive_local())
+}
+
+// Unix time of a local time, or None when a daylight saving change skips it.
+fn unix_time(time: chrono::NaiveDateTime) -> Option<i64> {
+    chrono::Local
+        .from_local_datetime(&time)
+        .earliest()
+        .map(|time| time.timestamp())
+}
+
+// The times a schedule was due after `since`, up to `now`, as unix times.
+fn due_times(cron: &cron::Cron, since: i64, now: i64) -> Vec<i64> {
+    let mut times = vec![];
+    let Some(mut time) = local_time(since) else {
+        return times;
+    };
+    while times.len() < MAX_MISSED_RUNS {
+        let Some(next) = cron.next_after(time) else {
+            break;
+        };
+        time = next;
+        match unix_time(next) {
+            Some(due) if due > now => break,
+            Some(due) => times.push(due),
+            None => {}
+        }
+    }
+    times
+}
+
+// Prints a line of the scheduler's log, after the time.
+fn scheduler_log(message: ColoredString) {
+    let time = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    println!("{} {}", time.dimmed(), message);
+}
+
+// A run started by the scheduler, sent back to be stored.
+struct ScheduledRun {
+    schedule_id: i64,
+    alias: String,
+    command_id: i64,
+    line: String,
+    code: i32,
+    duration: Duration,
+    attempts: Vec<Attempt>,
+    /// The last of the runs started for the schedule together.
+    last: bool,
+}
+
+// Runs scheduled commands when they are due until stopped, storing every run
+// in the run history. Schedules are read again every minute, so changes made
+// with `smriti schedule` apply without a restart.
+fn run_scheduler(conn: &Connection) -> i32 {
+    // Nobody is there to answer prompts for scheduled runs
+    prompt::set_enabled(false);
+    println!("{}", "Scheduler started, Ctrl-C to stop".cyan());
+    print_schedules(conn);
+
+    let (sender, receiver) = mpsc::channel::<ScheduledRun>();
+    let mut running = HashSet::new();
+    let mut started = 0;
+    loop {
+        let schedules = match retrieve_schedules(conn) {
+            Ok(schedules) => schedules,
+            Err(e) => {
+                eprintln!("{}", format!("Error retrieving schedules: {}", e).red());
+                return exit_code::DATABASE;
+            }
+        };
+        let now = chrono::Local::now().timestamp();
+        for schedule in schedules {
+            // Expressions are checked when they are saved
+            let Ok(cron) = cron::Cron::parse(&schedule.cron) else {
+                continue;
+            };
+            let since = schedule.last_run.unwrap_or(schedule.created_at);
+            let times = due_times(&cron, since, now);
+            let Some(&last) = times.last() else {
+                continue;
+            };
+            let handled = if times.len() == MAX_MISSED_RUNS {
+                now
+            } else {
+                last
+            };
+            if let Err(e) = update_schedule_last_run(conn, schedule.id, handled) {
+                eprintln!("{}", format!("Error saving schedule: {}", e).red());
+                return exit_code::DATABASE;
+            }
+
+            let on_time = now - last < SCHEDULE_GRACE;
+            let missed = times.len() - usize::from(on_time);
+            let (runs, policy) = match schedule.missed.as_str() {
+                "all" => (times.len(), "running them all"),
+                "once" => (1, "running it once"),
+                _ => (usize::from(on_time), "skipped"),
+            };
+            if missed > 0 {
+                let more = if times.len() == MAX_MISSED_RUNS {
+                    " or more"
+                } else {
+                    ""
+                };
+                scheduler_log(
+                    format!(
+                        "{} missed {}{} run(s) while the scheduler was not running, {}",
+                        schedule.alias, missed, more, policy
+                    )
+                    .yellow(),
+                );
+            }
+            if runs == 0 {
+                continue;
+            }
+            if running.contains(&schedule.id) {
+                scheduler_log(format!("{} is still running, skipped", schedule.alias).yellow());
+                continue;
+            }
+
+            let arg = RunCommand {
+                alias: Some(schedule.alias.clone()),
+                capture: true,
+                no_history: true,
+                yes: true,
+                ..Default::default()
+            };
+            let mut run = match retrieve_command_by_alias(conn, &schedule.alias) {
+                Ok(command) => match prepare_run(conn, command, &arg) {
+                    Ok(run) => run,
+                    Err(_) => {
+                        scheduler_log(format!("{} could not be started", schedule.alias).red());
+                        continue;
+                    }
+                },
+                Err(e) => {
+                    eprintln!("{}", format!("Error retrieving command: {}", e).red());
+                    continue;
+                }
+            };
+            let color = PREFIX_COLORS[started % PREFIX_COLORS.len()];
+            started += 1;
+            run.options.prefix = Some(format!("{} | ", schedule.alias.color(color)));
+            running.insert(schedule.id);
+            scheduler_log(format!("{} started", schedule.alias).cyan());
+
+            let sender = sender.clone();
+            let schedule_id = schedule.id;
+            thread::spawn(move || {
+                for count in 1..=runs {
+                    let begun = Instant::now();
+                    let mut attempts = vec![];
+                    let code = run_attempts(&run, |attempt, result| {
+                        attempts.push((
+                            attempt,
+                            result.duration,
+                            result.exit_code(),
+                            result.output.clone(),
+                        ))
+                    });
+                    let finished = ScheduledRun {
+                        schedule_id,
+                        alias: run.command.alias.clone(),
+                        command_id: run.command.id,
+                        line: run.line.clone(),
+                        code,
+                        duration: begun.elapsed(),
+                        attempts,
+                        last: count == runs,
+                    };
+                    if sender.send(finished).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        // Runs are stored as they finish, until the next minute starts
+        let millis = chrono::Local::now().timestamp_millis();
+        let next_minute = Instant::now() + Duration::from_millis(60_000 - (millis % 60_000) as u64);
+        while let Ok(finished) =
+            receiver.recv_timeout(next_minute.saturating_duration_since(Instant::now()))
+        {
+            record_attempts(conn, finished.command_id, &finished.line, finished.attempts);
+            if finished.last {
+                running.remove(&finished.schedule_id);
+            }
+            let duration = format!("{:.1}s", finished.duration.as_secs_f64());
+            match finished.code {
+                0 => scheduler_log(format!("{} ok in {}", finished.alias, duration).green()),
+                code => scheduler_log(
+                    format!("{} failed with {} in {}", finished.alias, code, duration).red(),
+                ),
+            }
+        }
+    }
+}
 
-#[cfg(test)]
-mod main_test;
+// Lists the schedules and when they run next.
+fn print_schedules(conn: &Connection) {
+    let schedules = match retrieve_schedules(conn) {
+        Ok(schedules) => schedules,
+        Err(e) => {
+            eprintln!("{}", format!("Error retrieving schedules: {}", e).red());
+            return;
+        }
+    };
+    if schedules.is_empty() {
+        println!("No schedules yet, add one with `smriti schedule add <alias> --cron ...`");
+        return;
+    }
+
+    let format = |time: chrono::NaiveDateTime| time.format("%Y-%m-%d %H:%M").to_string();
+    let now = chrono::Local::now().naive_local();
+    let mut table = vec![];
+    for schedule in schedules {
+        let next = cron::Cron::parse(&schedule.cron)
+            .ok()
+            .and_then(|cron| cron.next_after(now))
+            .map_or("never".to_string(), format);
+        let last = schedule
+            .last_run
+            .and_then(local_time)
+            .map_or("-".to_string(), format);
+        table.push(vec![
+            schedule.alias.cell(),
+            schedule.cron.cell(),
+            schedule.missed.cell(),
+            last.cell(),
+            next.cell(),
+        ]);
+    }
+
+    let table_display = table
+        .table()
+        .title(vec![
+            "Alias".cyan().cell().bold(true),
+            "Cron".cyan().cell().bold(true),
+            "Missed runs".cyan().cell().bold(true),
+            "Last run".cyan().cell().bold(true),
+            "Next run".cyan().cell().bold(true),
+        ])
+        .display()
+        .unwrap();
+
+    println!("{}", table_display);
+}
+
+// Lists stored runs, or prints the output of one of them.
+fn print_logs(conn: &Connection, logs: &LogsCommand) {
+    if let Some(id) = logs.id {
+        match retrieve_run(conn, id) {
+            Ok(run) => {
+                println!("{}", run.command.green());
+                match run.output {
+                    Some(output) => print!("{}", output),
+                    None => println!("{}", "Output was not captured for this run".yellow()),
+                }
+            }
+            Err(e) => eprintln!("{}", format!("Error retrieving run {}: {}", id, e).red()),
+        }
+        return;
+    }
+
+    let runs = match logs.alias {
+        Some(ref alias) => retrieve_command_by_alias(conn, alias)
+            .and_then(|command| retrieve_runs(conn, Some(command.id), logs.limit)),
+        None => retrieve_runs(conn, None, logs.limit),
+    };
+
+    match runs {
+        Ok(runs) => {
+            let mut table = vec![];
+            for run in runs {
+                let exit_code = run
+                    .exit_code
+                    .map(|code| code.to_string())
+                    .unwrap_or_else(|| "-".to_string());
+                table.push(vec![
+                    run.id.cell(),
+                    run.alias.cell(),
+                    run.started_at.cell(),
+                    exit_code.cell(),
+                    run.attempt.cell(),
+                    format!("{:.1}s", run.duration_ms as f64 / 1000.0).cell(),
+                    run.command.cell(),
+                ]);
+            }
+
+            let table_display = table
+                .table()
+                .title(vec![
+                    "Id".cyan().cell().bold(true),
+                    "Alias".cyan().cell().bold(true),
+                    "Started".cyan().cell().bold(true),
+                    "Exit".cyan().cell().bold(true),
+                    "Attempt".cyan().cell().bold(true),
+                    "Duration".cyan().cell().bold(true),
+                    "Command".cyan().cell().bold(true),
+                ])
+                .display()
+                .unwrap();
+
+            println!("{}", table_display);
+        }
+        Err(e) => eprintln!(
+            "{}",
+            format!(
+                "Error retrieving runs: {} \nNo command associated with alias: {:?}",
+                e, logs.alias
+            )
+            .red()
+        ),
+    }
+}
+
+// Reads the .env file in the current directory, if there is one.
+fn load_dotenv() -> HashMap<String, String> {
+    match dotenvy::from_path_iter(".env") {
+        Ok(iter) => iter.filter_map(|item| item.ok()).collect(),
+        Err(_) => HashMap::new(),
+    }
+}
+
+pub fn print_resolved(resolved: &[Resolved]) {
+    let mut table = vec![];
+    for placeholder in resolved {
+        table.push(vec![
+            placeholder.name.clone().cell(),
+            placeholder.value().cell(),
+            placeholder.source.to_string().cell(),
+        ]);
+    }
+
+    let table_display = table
+        .table()
+        .title(vec![
+            "Placeholder".cyan().cell().bold(true),
+            "Value".cyan().cell().bold(true),
+            "Source".cyan().cell().bold(true),
+        ])
+        .display()
+        .unwrap();
+
+    println!("{}", table_display);
+}
 
 pub fn create_table_header(table: Vec<Vec<CellStruct>>) -> TableDisplay {
     table
@@ -29,64 +1904,153 @@ pub fn create_table_header(table: Vec<Vec<CellStruct>>) -> TableDisplay {
         .unwrap()
 }
 
-fn main() -> Result<()> {
-    let conn: Connection = create_conn()?;
+fn main() {
+    let conn: Connection = match create_conn() {
+        Ok(conn) => conn,
+        Err(e) => {
+            eprintln!("{}", format!("Error opening database: {}", e).red());
+            process::exit(exit_code::DATABASE);
+        }
+    };
 
     let cli = SmritiCli::parse();
+    process::exit(run_cli(conn, cli.command));
+}
 
-    match cli.command {
-        CliArgs::Run(arg) => match retrieve_command(&conn, &arg.alias) {
-            Ok(command) => match replace_placeholder(&command, arg.variables) {
-                Ok(modified_command) => {
-                    if let Err(e) = shell_executor::execute_command(modified_command) {
-                        eprintln!(
-                            "{}",
-                            format!("Error executing command: check variables passed {}", e).red()
-                        );
+// Runs a smriti command and returns the exit code smriti should exit with.
+fn run_cli(conn: Connection, command: CliArgs) -> i32 {
+    match command {
+        CliArgs::Run(arg) if arg.help => print_usage(&conn, arg.alias.as_deref()),
+
+        CliArgs::Run(arg) => {
+            return match arg.service.clone() {
+                Some(service) => run_service(&conn, &service, arg),
+                None => run_alias(&conn, arg, &mut vec![]),
+            }
+        }
+
+        CliArgs::Add(mut add_command) => {
+            if add_command.edit {
+                let file = editor::ScriptFile {
+                    alias: add_command.alias.clone(),
+                    info: add_command.info.clone(),
+                    service: add_command.service.clone(),
+                    script: add_command.command.clone(),
+                };
+                match edit_script(file) {
+                    Ok(file) => {
+                        add_command.alias = file.alias;
+                        add_command.info = file.info;
+                        add_command.service = file.service;
+                        add_command.command = file.script;
+                    }
+                    Err(e) => {
+                        eprintln!("{}", format!("Error editing script: {}", e).red());
+                        return exit_code::USAGE;
                     }
                 }
-                Err(err) => {
-                    eprintln!("{}", format!("Error retrieving command: {}", err).red())
-                }
-            },
-            Err(err) => eprintln!("{}", format!("Error retrieving command: {}", err).red()),
-        },
-
-        CliArgs::Add(add_command) => {
+            }
             let add_args = &add_command;
-            match insert_command(
+            let command_text = if add_args.parameterize {
+                parameterize_command(&add_args.command)
+            } else {
+                add_args.command.clone()
+            };
+            match check_saved_template(
                 &conn,
-                &add_args.command,
                 &add_args.alias,
-                add_args.info.as_ref().unwrap(),
-                &add_args.service,
+                Some(&command_text),
+                Some(add_args.template),
             ) {
-                Ok(()) => {
-                    match retrieve_command_by_alias(&conn, &add_args.alias) {
-                        Ok(command) => {
-                            let table = vec![vec![
-                                command.id.cell(),
-                                command.alias.cell(),
-                                command.command.cell(),
-                                command.info.cell(),
-                                command.service.cell(),
-                            ]];
-                            let table_display = create_table_header(table);
-                            println!("{}", table_display);
-                        }
-                        Err(e) => {
-                            eprintln!("{}",format!(
-                                "Error retrieving command: {} \nNo command associated with alias: {:?}",
-                                e, &add_args.alias).red()
-                            );
-                        }
+                Ok(false) => print_brace_warnings(&command_text),
+                Ok(true) => {}
+                Err(e) => {
+                    eprintln!("{}", format!("Error in template: {}", e).red());
+                    return exit_code::USAGE;
+                }
+            }
+            if let Err(e) = check_saved_workflow(
+                &conn,
+                &add_args.alias,
+                Some(&command_text),
+                Some(add_args.workflow),
+            ) {
+                eprintln!("{}", format!("Error in workflow: {}", e).red());
+                return exit_code::USAGE;
+            }
+            if let Some(ref shell) = add_args.shell {
+                if let Err(e) = shell_executor::check_interpreter(shell) {
+                    eprintln!("{}", format!("{}", e).red());
+                    return exit_code::USAGE;
+                }
+            }
+            if let Err(e) = check_env_names(&add_args.env) {
+                eprintln!("{}", format!("Error saving environment: {}", e).red());
+                return exit_code::USAGE;
+            }
+            check_placeholder_specs(
+                &command_text,
+                &add_args.alias,
+                &add_args.generator,
+                &add_args.column,
+                &add_args.describe,
+            );
+            let dangerous = config::is_dangerous(&conn, &command_text);
+            let detected = match add_args.workflow || add_args.interactive {
+                true => Interactive::No,
+                false => shell_executor::looks_interactive(&command_text),
+            };
+            let saved = save_added_command(
+                &conn,
+                add_args,
+                &command_text,
+                add_args.confirm || dangerous,
+                add_args.interactive || detected == Interactive::Yes,
+            );
+            match saved {
+                Ok(command) => {
+                    if dangerous && !add_args.confirm {
+                        println!(
+                            "{}",
+                            "Looks dangerous, it will ask for confirmation before it runs (update --confirm false to turn off)"
+                                .yellow()
+                        );
+                    }
+                    if detected == Interactive::Maybe {
+                        println!(
+                            "{}",
+                            format!(
+                                "If it needs the terminal, mark it with `smriti update -a {} --interactive true`",
+                                add_args.alias
+                            )
+                            .yellow()
+                        );
+                    }
+                    if detected == Interactive::Yes {
+                        println!(
+                            "{}",
+                            "Marked as interactive, it will always run attached to the terminal"
+                                .yellow()
+                        );
                     }
+                    let table = vec![vec![
+                        command.id.cell(),
+                        command.alias.cell(),
+                        command.command.cell(),
+                        command.info.cell(),
+                        command.service.cell(),
+                    ]];
+                    let table_display = create_table_header(table);
+                    println!("{}", table_display);
                     println!("Saved successfully!")
                 }
-                Err(err) => eprintln!(
-                    "Error inserting data: {} ",
-                    format!("{} \n Note: Command and Alias must be unique", err).red()
-                ),
+                Err(err) => {
+                    eprintln!(
+                        "Error inserting data: {} ",
+                        format!("{} \n Note: Command and Alias must be unique", err).red()
+                    );
+                    return database_exit_code(&err);
+                }
             }
         }
 
@@ -167,6 +2131,8 @@ fn main() -> Result<()> {
                 if let Some(ref alias) = show_command.alias {
                     match retrieve_command_by_alias(&conn, alias) {
                         Ok(command) => {
+                            let env = retrieve_command_env(&conn, command.id).unwrap_or_default();
+                            let settings = run_settings(&command, &env);
                             let table = vec![vec![
                                 command.id.cell(),
                                 command.alias.cell(),
@@ -176,6 +2142,9 @@ fn main() -> Result<()> {
                             ]];
                             let table_display = create_table_header(table);
                             println!("{}", table_display);
+                            for (name, value) in settings {
+                                println!("{} {}", format!("{}:", name).cyan().bold(), value);
+                            }
                         }
                         Err(e) => {
                             eprintln!("{}",format!(
@@ -277,109 +2246,212 @@ fn main() -> Result<()> {
             }
         }
 
-        CliArgs::Update(update_command) => {
+        CliArgs::Update(mut update_command) => {
+            // A new alias written in the header of the script, applied last
+            let mut new_alias = None;
+            if update_command.edit && !update_command.alias.is_empty() {
+                let command = match retrieve_command_by_alias(&conn, &update_command.alias) {
+                    Ok(command) => command,
+                    Err(e) => {
+                        eprintln!("{}", format!("Error retrieving command: {}", e).red());
+                        return database_exit_code(&e);
+                    }
+                };
+                let file = editor::ScriptFile {
+                    alias: command.alias.clone(),
+                    info: Some(command.info.clone()).filter(|info| !info.is_empty()),
+                    service: command.service.clone(),
+                    script: command.command.clone(),
+                };
+                match edit_script(file) {
+                    Ok(file) => {
+                        let info = file.info.unwrap_or_default();
+                        if info != command.info {
+                            update_command.info = Some(info);
+                        }
+                        if file.service != command.service {
+                            update_command.service = Some(file.service);
+                        }
+                        if file.alias != command.alias {
+                            new_alias = Some(file.alias);
+                        }
+                        update_command.command = Some(file.script);
+                    }
+                    Err(e) => {
+                        eprintln!("{}", format!("Error editing script: {}", e).red());
+                        return exit_code::USAGE;
+                    }
+                }
+            }
             if !update_command.alias.is_empty() {
                 if update_command.command.is_some()
                     || update_command.info.is_some()
                     || update_command.service.is_some()
+                    || update_command.template.is_some()
+                    || update_command.workflow.is_some()
+                    || update_command.interactive.is_some()
+                    || update_command.shell.is_some()
+                    || update_command.cwd.is_some()
+                    || !update_command.env.is_empty()
+                    || !update_command.unset_env.is_empty()
+                    || update_command.clear_env.is_some()
+                    || update_command.confirm.is_some()
+                    || update_command.timeout.is_some()
+                    || update_command.retries.is_some()
+                    || update_command.retry_delay.is_some()
+                    || update_command.edit
+                    || !update_command.generator.is_empty()
+                    || !update_command.column.is_empty()
+                    || !update_command.describe.is_empty()
                 {
-                    if let Some(ref command) = update_command.command {
-                        match update_command_by_alias(&conn, &update_command.alias, command) {
-                            Ok(()) => match retrieve_command_by_alias(&conn, &update_command.alias)
-                            {
-                                Ok(command) => {
-                                    let table = vec![vec![
-                                        command.id.cell(),
-                                        command.alias.cell(),
-                                        command.command.cell(),
-                                        command.info.cell(),
-                                        command.service.cell(),
-                                    ]];
-                                    let table_display = create_table_header(table);
-                                    println!("{}", table_display);
-                                }
-                                Err(e) => {
-                                    eprintln!("{}",format!(
-                                            "Error retrieving command: {} \nNo command associated with alias: {:?}",
-                                            e, &update_command.alias).red()
-                                        );
-                                }
-                            },
+                    let template = match check_saved_template(
+                        &conn,
+                        &update_command.alias,
+                        update_command.command.as_deref(),
+                        update_command.template,
+                    ) {
+                        Ok(template) => template,
+                        Err(e) => {
+                            eprintln!("{}", format!("Error in template: {}", e).red());
+                            return exit_code::USAGE;
+                        }
+                    };
+
+                    if let Err(e) = check_saved_workflow(
+                        &conn,
+                        &update_command.alias,
+                        update_command.command.as_deref(),
+                        update_command.workflow,
+                    ) {
+                        eprintln!("{}", format!("Error in workflow: {}", e).red());
+                        return exit_code::USAGE;
+                    }
+
+                    // An empty interpreter goes back to the default shell
+                    let shell = update_command
+                        .shell
+                        .as_deref()
+                        .filter(|shell| !shell.is_empty());
+                    if let Some(Err(e)) = shell.map(shell_executor::check_interpreter) {
+                        eprintln!("{}", format!("{}", e).red());
+                        return exit_code::USAGE;
+                    }
+
+                    if let Err(e) = check_env_names(&update_command.env) {
+                        eprintln!("{}", format!("Error saving environment: {}", e).red());
+                        return exit_code::USAGE;
+                    }
+
+                    if !update_command.generator.is_empty()
+                        || !update_command.column.is_empty()
+                        || !update_command.describe.is_empty()
+                    {
+                        match retrieve_command_by_alias(&conn, &update_command.alias) {
+                            Ok(command) => check_placeholder_specs(
+                                update_command
+                                    .command
+                                    .as_deref()
+                                    .unwrap_or(&command.command),
+                                &update_command.alias,
+                                &update_command.generator,
+                                &update_command.column,
+                                &update_command.describe,
+                            ),
                             Err(e) => {
-                                println!(
-                                    "{}",
-                                    format!("{}\n Couldn't update at the moment", e).red()
-                                );
+                                eprintln!("{}", format!("Error retrieving command: {}", e).red());
+                                return database_exit_code(&e);
                             }
                         }
                     }
 
-                    if let Some(ref service) = update_command.service {
-                        match update_service_by_alias(&conn, &update_command.alias, service) {
-                            Ok(()) => {
-                                match retrieve_command_by_alias(&conn, &update_command.alias) {
-                                    Ok(command) => {
-                                        let table = vec![vec![
-                                            command.id.cell(),
-                                            command.alias.cell(),
-                                            command.command.cell(),
-                                            command.info.cell(),
-                                            command.service.cell(),
-                                        ]];
-                                        let table_display = create_table_header(table);
-                                        println!("{}", table_display);
-                                    }
-                                    Err(e) => {
-                                        eprintln!(
-                                            "Error retrieving command: {} \nNo command associated with alias: {:?}",
-                                            e, &update_command.alias
-                                        );
+                    if let Some(ref command) = update_command.command {
+                        if !template {
+                            print_brace_warnings(command);
+                        }
+                    }
+
+                    // A new command text gets the checks add makes, unless set explicitly
+                    let mut notes = vec![];
+                    if let Some(ref text) = update_command.command {
+                        let saved = match retrieve_command_by_alias(&conn, &update_command.alias) {
+                            Ok(command) => command,
+                            Err(e) => {
+                                eprintln!("{}", format!("Error retrieving command: {}", e).red());
+                                return database_exit_code(&e);
+                            }
+                        };
+                        if *text != saved.command {
+                            if update_command.confirm.is_none()
+                                && !saved.confirm
+                                && config::is_dangerous(&conn, text)
+                            {
+                                update_command.confirm = Some(true);
+                                notes.push("Looks dangerous, it will ask for confirmation before it runs (update --confirm false to turn off)".to_string());
+                            }
+                            let workflow = update_command.workflow.unwrap_or(saved.workflow);
+                            if update_command.interactive.is_none()
+                                && !saved.interactive
+                                && !workflow
+                            {
+                                match shell_executor::looks_interactive(text) {
+                                    Interactive::Yes => {
+                                        update_command.interactive = Some(true);
+                                        notes.push("Marked as interactive, it will always run attached to the terminal".to_string());
                                     }
+                                    Interactive::Maybe => notes.push(format!(
+                                        "If it needs the terminal, mark it with `smriti update -a {} --interactive true`",
+                                        update_command.alias
+                                    )),
+                                    Interactive::No => {}
                                 }
                             }
-                            Err(e) => {
-                                println!(
-                                    "{}",
-                                    format!("{}\n Couldn't update at the moment", e).red()
-                                );
+                        }
+                    }
+
+                    match save_updated_command(&conn, &update_command, new_alias.as_deref()) {
+                        Ok(saved) => {
+                            for note in notes {
+                                println!("{}", note.yellow());
                             }
+                            for line in saved {
+                                println!("{}", line);
+                            }
+                        }
+                        Err(e) => {
+                            println!("{}", format!("{}\n Couldn't update at the moment", e).red());
+                            return error_exit_code(&*e);
                         }
                     }
 
-                    if let Some(ref info) = update_command.info {
-                        match update_info_by_alias(&conn, &update_command.alias, info) {
-                            Ok(()) => {
-                                match retrieve_command_by_alias(&conn, &update_command.alias) {
-                                    Ok(command) => {
-                                        let table = vec![vec![
-                                            command.id.cell(),
-                                            command.alias.cell(),
-                                            command.command.cell(),
-                                            command.info.cell(),
-                                            command.service.cell(),
-                                        ]];
-                                        let table_display = create_table_header(table);
-                                        println!("{}", table_display);
-                                    }
-                                    Err(e) => {
-                                        eprintln!(
-                                            "Error retrieving command: {} \nNo command associated with alias: {:?}",
-                                            e, &update_command.alias
-                                        );
-                                    }
-                                }
+                    if update_command.command.is_some()
+                        || update_command.service.is_some()
+                        || update_command.info.is_some()
+                    {
+                        let alias = new_alias.as_ref().unwrap_or(&update_command.alias);
+                        match retrieve_command_by_alias(&conn, alias) {
+                            Ok(command) => {
+                                let table = vec![vec![
+                                    command.id.cell(),
+                                    command.alias.cell(),
+                                    command.command.cell(),
+                                    command.info.cell(),
+                                    command.service.cell(),
+                                ]];
+                                let table_display = create_table_header(table);
+                                println!("{}", table_display);
                             }
                             Err(e) => {
-                                println!(
-                                    "{}",
-                                    format!("{}\n Couldn't update at the moment", e).red()
-                                );
+                                eprintln!("{}",format!(
+                                        "Error retrieving command: {} \nNo command associated with alias: {:?}",
+                                        e, alias).red()
+                                    );
                             }
                         }
                     }
                 }
             } else {
                 println!("{}", "Required -a flag not provided".red());
+                return exit_code::USAGE;
             }
         }
 
@@ -419,11 +2491,306 @@ fn main() -> Result<()> {
                     );
                 }
             }
-        } // _ => {
-          //     println!("{}", format!("Command not implemented yet").red());
-          //     // default logic or placeholder for other commands}
-          // }
+        }
+
+        CliArgs::Var(var_command) => match var_command.action {
+            VarAction::Set {
+                name,
+                value,
+                service,
+            } => match set_variable(&conn, &name, &value, service.as_deref()) {
+                Ok(()) => println!("Variable {} saved successfully!", name),
+                Err(e) => eprintln!("{}", format!("Error saving variable: {}", e).red()),
+            },
+            VarAction::Get { name, service } => {
+                match get_variable(&conn, &name, service.as_deref()) {
+                    Ok(value) => println!("{}", value),
+                    Err(e) => eprintln!(
+                        "{}",
+                        format!(
+                            "Error retrieving variable: {} \nNo variable named: {:?}",
+                            e, name
+                        )
+                        .red()
+                    ),
+                }
+            }
+            VarAction::List { service } => match list_variables(&conn, service.as_deref()) {
+                Ok(variables) => {
+                    let mut table = vec![];
+                    for variable in variables {
+                        let scope = if variable.service.is_empty() {
+                            "global".to_string()
+                        } else {
+                            variable.service
+                        };
+                        table.push(vec![
+                            variable.name.cell(),
+                            variable.value.cell(),
+                            scope.cell(),
+                        ]);
+                    }
+
+                    let table_display = table
+                        .table()
+                        .title(vec![
+                            "Name".cyan().cell().bold(true),
+                            "Value".cyan().cell().bold(true),
+                            "Service".cyan().cell().bold(true),
+                        ])
+                        .display()
+                        .unwrap();
+
+                    println!("{}", table_display);
+                }
+                Err(e) => eprintln!("{}", format!("{}", e).red()),
+            },
+            VarAction::Unset { name, service } => {
+                match unset_variable(&conn, &name, service.as_deref()) {
+                    Ok(0) => println!("{}", format!("No variable named {:?}", name).red()),
+                    Ok(_) => println!("Variable {} removed", name),
+                    Err(e) => eprintln!("{}", format!("Error removing variable: {}", e).red()),
+                }
+            }
+        },
+
+        CliArgs::Usage(usage_command) => print_usage(&conn, Some(&usage_command.alias)),
+
+        CliArgs::Logs(logs_command) => print_logs(&conn, &logs_command),
+
+        CliArgs::Config(config_command) => match config_command.action {
+            ConfigAction::Set { key, value } => {
+                if let Err(e) = config::check_setting(&key, &value) {
+                    eprintln!("{}", format!("{}", e).red());
+                    return exit_code::USAGE;
+                }
+                match set_setting(&conn, &key, &value) {
+                    Ok(()) => println!("Setting {} saved successfully!", key),
+                    Err(e) => {
+                        eprintln!("{}", format!("Error saving setting: {}", e).red());
+                        return exit_code::DATABASE;
+                    }
+                }
+            }
+            ConfigAction::Get { key } => match get_setting(&conn, &key) {
+                Ok(value) => println!("{}", value),
+                Err(e) => eprintln!(
+                    "{}",
+                    format!(
+                        "Error retrieving setting: {} \nNo value set for: {:?}",
+                        e, key
+                    )
+                    .red()
+                ),
+            },
+            ConfigAction::List => match list_settings(&conn) {
+                Ok(settings) => {
+                    let mut table = vec![];
+                    for (key, description, default) in config::SETTINGS {
+                        let value = settings
+                            .iter()
+                            .find(|setting| setting.key == *key)
+                            .map(|setting| setting.value.clone())
+                            .unwrap_or_else(|| default.to_string());
+                        table.push(vec![key.cell(), value.cell(), description.cell()]);
+                    }
+
+                    let table_display = table
+                        .table()
+                        .title(vec![
+                            "Key".cyan().cell().bold(true),
+                            "Value".cyan().cell().bold(true),
+                            "Description".cyan().cell().bold(true),
+                        ])
+                        .display()
+                        .unwrap();
+
+                    println!("{}", table_display);
+                }
+                Err(e) => eprintln!("{}", format!("{}", e).red()),
+            },
+            ConfigAction::Unset { key } => match unset_setting(&conn, &key) {
+                Ok(0) => println!("{}", format!("No value set for {:?}", key).red()),
+                Ok(_) => println!("Setting {} reset to its default", key),
+                Err(e) => eprintln!("{}", format!("Error removing setting: {}", e).red()),
+            },
+        },
+
+        CliArgs::History(history_command) => match history_command.action {
+            HistoryAction::List { alias, placeholder } => {
+                match retrieve_command_by_alias(&conn, &alias).and_then(|command| {
+                    retrieve_placeholder_history(&conn, command.id, placeholder.as_deref())
+                }) {
+                    Ok(history) => {
+                        let mut table = vec![];
+                        for value in history {
+                            table.push(vec![
+                                value.name.cell(),
+                                value.value.cell(),
+                                value.uses.cell(),
+                                value.last_used.cell(),
+                            ]);
+                        }
+
+                        let table_display = table
+                            .table()
+                            .title(vec![
+                                "Placeholder".cyan().cell().bold(true),
+                                "Value".cyan().cell().bold(true),
+                                "Uses".cyan().cell().bold(true),
+                                "Last used".cyan().cell().bold(true),
+                            ])
+                            .display()
+                            .unwrap();
+
+                        println!("{}", table_display);
+                    }
+                    Err(e) => eprintln!(
+                        "{}",
+                        format!(
+                            "Error retrieving history: {} \nNo command associated with alias: {:?}",
+                            e, alias
+                        )
+                        .red()
+                    ),
+                }
+            }
+            HistoryAction::Forget {
+                alias,
+                placeholder,
+                value,
+            } => match retrieve_command_by_alias(&conn, &alias).and_then(|command| {
+                forget_placeholder_values(
+                    &conn,
+                    command.id,
+                    placeholder.as_deref(),
+                    value.as_deref(),
+                )
+            }) {
+                Ok(count) => println!("Forgot {} remembered value(s) of {}", count, alias),
+                Err(e) => eprintln!(
+                    "{}",
+                    format!(
+                        "Error forgetting values: {} \nNo command associated with alias: {:?}",
+                        e, alias
+                    )
+                    .red()
+                ),
+            },
+        },
+
+        CliArgs::Schedule(schedule_command) => match schedule_command.action {
+            ScheduleAction::Add {
+                alias,
+                cron,
+                missed,
+                yes,
+            } => {
+                let next = match cron::Cron::parse(&cron) {
+                    Ok(parsed) => parsed.next_after(chrono::Local::now().naive_local()),
+                    Err(e) => {
+                        eprintln!("{}", format!("Invalid cron expression: {}", e).red());
+                        return exit_code::USAGE;
+                    }
+                };
+                let Some(next) = next else {
+                    eprintln!("{}", format!("`{}` never runs", cron).red());
+                    return exit_code::USAGE;
+                };
+                let command = match retrieve_command_by_alias(&conn, &alias) {
+                    Ok(command) => command,
+                    Err(e) => {
+                        eprintln!(
+                            "{}",
+                            format!(
+                                "Error retrieving command: {} \nNo command associated with alias: {:?}",
+                                e, alias
+                            )
+                            .red()
+                        );
+                        return database_exit_code(&e);
+                    }
+                };
+                if command.workflow || command.interactive {
+                    let kind = if command.workflow {
+                        "a workflow"
+                    } else {
+                        "interactive"
+                    };
+                    eprintln!(
+                        "{}",
+                        format!("{} is {}, it cannot be scheduled", alias, kind).red()
+                    );
+                    return exit_code::USAGE;
+                }
+
+                // Scheduled runs cannot ask for values, so they must resolve without asking
+                // now, which also leaves the generators alone
+                prompt::set_enabled(false);
+                let resolved = working_directory(&command, &RunCommand::default())
+                    .and_then(|cwd| resolve_command(&conn, &command, vec![], &[], cwd.as_deref()));
+                prompt::set_enabled(true);
+                let line = match resolved {
+                    Ok(resolved) => resolved.command,
+                    Err(e) => {
+                        eprintln!(
+                            "{}",
+                            format!(
+                                "{} cannot run unattended: {}\nGive its placeholders defaults, or values with `smriti var set`",
+                                alias, e
+                            )
+                            .red()
+                        );
+                        return error_exit_code(&*e);
+                    }
+                };
+                // Confirmed once here, as nobody is there when it runs
+                if command.confirm && !yes && !confirmed(&command, &line) {
+                    return exit_code::NOT_CONFIRMED;
+                }
+
+                match set_schedule(&conn, command.id, &cron, &missed) {
+                    Ok(()) => println!(
+                        "Scheduled {} ({}), next run at {} while `smriti scheduler` is running",
+                        alias,
+                        cron,
+                        next.format("%Y-%m-%d %H:%M")
+                    ),
+                    Err(e) => {
+                        eprintln!("{}", format!("Error saving schedule: {}", e).red());
+                        return exit_code::DATABASE;
+                    }
+                }
+            }
+            ScheduleAction::List => print_schedules(&conn),
+            ScheduleAction::Remove { alias } => match retrieve_command_by_alias(&conn, &alias)
+                .and_then(|command| delete_schedule(&conn, command.id))
+            {
+                Ok(0) => println!("{} has no schedule", alias),
+                Ok(_) => println!("Removed the schedule of {}", alias),
+                Err(e) => eprintln!(
+                    "{}",
+                    format!(
+                        "Error removing schedule: {} \nNo command associated with alias: {:?}",
+                        e, alias
+                    )
+                    .red()
+                ),
+            },
+        },
+
+        CliArgs::Scheduler => return run_scheduler(&conn),
+
+        CliArgs::Complete(complete_command) => print_completions(
+            &conn,
+            complete_command.alias.as_deref(),
+            complete_command.position,
+        ),
+        // _ => {
+        //     println!("{}", format!("Command not implemented yet").red());
+        //     // default logic or placeholder for other commands}
+        // }
     }
 
-    Ok(())
+    0
 }
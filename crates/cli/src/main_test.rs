@@ -1,22 +1,17 @@
 #[cfg(test)]
 mod tests {
-    use crate::{CliArgs, SmritiCli};
+    use crate::exit_code;
+    use crate::placeholder::Source;
+    use crate::shell_executor::ExecOptions;
+    use crate::{CliArgs, SmritiCli, VarAction};
     use clap::Parser;
+    use cli::RunCommand;
     use database::*;
     use rusqlite::{Connection, Result};
 
     pub fn db_connect() -> Result<Connection> {
         let conn = Connection::open_in_memory()?;
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS commands (
-                 id INTEGER PRIMARY KEY,
-                 command TEXT NOT NULL UNIQUE,
-                 alias TEXT UNIQUE,
-                 info TEXT,
-                 service TEXT
-            )",
-            [],
-        )?;
+        create_tables(&conn)?;
         Ok(conn) // Return the connection
     }
 
@@ -31,7 +26,7 @@ mod tests {
     fn test_rename_alias() -> Result<()> {
         let conn = db_connect()?;
 
-        let matches = SmritiCli::parse_from(&["smriti", "rename", ALIAS, NEW_ALIAS]);
+        let matches = SmritiCli::parse_from(["smriti", "rename", ALIAS, NEW_ALIAS]);
         if let CliArgs::Rename(rename) = matches.command {
             assert_eq!(rename.alias, ALIAS);
             assert_eq!(rename.new_alias, NEW_ALIAS);
@@ -53,7 +48,7 @@ mod tests {
         insert_command(&conn, COMMAND, ALIAS, INFO, SERVICE)?;
 
         // Now, test deleting the alias
-        let matches = SmritiCli::parse_from(&["smriti", "delete", "-a", ALIAS]);
+        let matches = SmritiCli::parse_from(["smriti", "delete", "-a", ALIAS]);
 
         if let CliArgs::Delete(_delete) = matches.command {
             delete_by_alias(&conn, ALIAS)?;
@@ -73,7 +68,7 @@ mod tests {
         let conn = db_connect()?;
         insert_command(&conn, COMMAND, ALIAS, INFO, SERVICE)?;
 
-        let matches = SmritiCli::parse_from(&["smriti", "delete", "-s", SERVICE]);
+        let matches = SmritiCli::parse_from(["smriti", "delete", "-s", SERVICE]);
 
         if let CliArgs::Delete(_delete) = matches.command {
             delete_by_service(&conn, SERVICE)?;
@@ -87,4 +82,856 @@ mod tests {
         }
         Ok(())
     }
+
+    // The arguments of `smriti run <alias>`.
+    fn run_args(alias: &str) -> RunCommand {
+        RunCommand {
+            alias: Some(alias.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_variables() -> Result<()> {
+        let conn = db_connect()?;
+
+        let matches = SmritiCli::parse_from(["smriti", "var", "set", "registry", "ghcr.io"]);
+        if let CliArgs::Var(var) = matches.command {
+            assert!(matches!(var.action, VarAction::Set { service: None, .. }));
+        } else {
+            panic!("Expected Var command");
+        }
+
+        set_variable(&conn, "registry", "docker.io", None)?;
+        set_variable(&conn, "registry", "registry.local", Some(SERVICE))?;
+        insert_command(
+            &conn,
+            "docker push {registry}/{image}:{tag=latest}",
+            ALIAS,
+            INFO,
+            SERVICE,
+        )?;
+        let command = retrieve_command_by_alias(&conn, ALIAS)?;
+        let resolve = |named: &[(&str, &str)]| {
+            let named: Vec<(String, String)> = named
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            crate::resolve_command(&conn, &command, vec![], &named, None)
+                .map(|resolved| resolved.command)
+        };
+
+        // --set, then the service's variables, then global ones, then defaults
+        assert_eq!(
+            resolve(&[("image", "app")]).unwrap(),
+            "docker push registry.local/app:latest"
+        );
+        assert_eq!(
+            resolve(&[("image", "app"), ("registry", "ghcr.io"), ("tag", "v2")]).unwrap(),
+            "docker push ghcr.io/app:v2"
+        );
+        unset_variable(&conn, "registry", Some(SERVICE))?;
+        assert_eq!(
+            resolve(&[("image", "app")]).unwrap(),
+            "docker push docker.io/app:latest"
+        );
+        assert!(resolve(&[]).is_err(), "{{image}} has no value");
+
+        // Only a missing variable is "not set", other errors stop the run
+        let found = crate::lookup_variable(&conn, "registry", SERVICE)?;
+        assert_eq!(
+            found,
+            Some(("docker.io".to_string(), Source::GlobalVariable))
+        );
+        assert_eq!(crate::lookup_variable(&conn, "missing", SERVICE)?, None);
+        conn.execute("DROP TABLE variables", [])?;
+        assert!(crate::lookup_variable(&conn, "registry", SERVICE).is_err());
+        assert!(resolve(&[("image", "app")]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_placeholder_generators() -> Result<()> {
+        use crate::shell_executor::generate_choices;
+
+        let matches = SmritiCli::parse_from([
+            "smriti",
+            "add",
+            "-c",
+            "docker logs {container}",
+            "-a",
+            ALIAS,
+            "-s",
+            SERVICE,
+            "--generator",
+            "container=docker ps --format '{{.Names}}'",
+            "--column",
+            "container=1",
+        ]);
+        if let CliArgs::Add(add) = matches.command {
+            assert_eq!(add.generator[0].0, "container");
+            assert_eq!(add.column, vec![("container".to_string(), 1)]);
+        } else {
+            panic!("Expected Add command");
+        }
+
+        let choices = generate_choices("printf 'web  Up\\n\\napi  Exited\\n'", Some(1)).unwrap();
+        assert_eq!(choices, ["web", "api"]);
+        assert!(generate_choices("echo oops >&2; exit 1", None).is_err());
+
+        // Without a terminal to choose on, the placeholder has no value
+        let conn = db_connect()?;
+        insert_command(&conn, "docker logs {container}", ALIAS, INFO, SERVICE)?;
+        let command = retrieve_command_by_alias(&conn, ALIAS)?;
+        set_placeholder_generator(&conn, command.id, "container", "echo web")?;
+        assert!(crate::resolve_command(&conn, &command, vec![], &[], None).is_err());
+        let resolved = crate::resolve_command(&conn, &command, strings(&["api"]), &[], None);
+        assert_eq!(resolved.unwrap().command, "docker logs api");
+
+        // Without a terminal to choose on, as for scheduled runs, generators are not run
+        let marker = std::env::temp_dir().join(format!("smriti-generator-{}", std::process::id()));
+        let generator = format!("touch {}", marker.display());
+        set_placeholder_generator(&conn, command.id, "container", &generator)?;
+        assert!(crate::resolve_command(&conn, &command, vec![], &[], None).is_err());
+        assert!(!marker.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_usage() -> Result<()> {
+        let conn = db_connect()?;
+
+        let matches = SmritiCli::parse_from(["smriti", "run", ALIAS, "--help"]);
+        if let CliArgs::Run(run) = matches.command {
+            assert!(run.help);
+            assert_eq!(run.alias.as_deref(), Some(ALIAS));
+        } else {
+            panic!("Expected Run command");
+        }
+
+        insert_command(
+            &conn,
+            "scp {file} {host=backup}:{dest...}",
+            ALIAS,
+            INFO,
+            SERVICE,
+        )?;
+        let command = retrieve_command_by_alias(&conn, ALIAS)?;
+        set_placeholder_description(&conn, command.id, "file", "File to copy")?;
+
+        let specs = retrieve_placeholder_specs(&conn, command.id)?;
+        let text = crate::usage::usage_text(&command, &specs);
+        assert!(text.contains("smriti run init_alias <file> [host] [dest]..."));
+        assert!(text.contains("File to copy"));
+        assert!(text.contains("default: backup"));
+        assert!(text.contains(INFO));
+        assert!(text.contains("smriti run init_alias <file> backup\n"));
+
+        // Example values are quoted so the line runs as it is
+        update_command_by_alias(&conn, ALIAS, "grep {pattern=a b} {file=it's.log}")?;
+        let command = retrieve_command_by_alias(&conn, ALIAS)?;
+        let text = crate::usage::usage_text(&command, &[]);
+        assert!(text.contains(r"smriti run init_alias 'a b' 'it'\''s.log'"));
+
+        // Printed template variables are arguments, the others are set by name
+        update_command_by_alias(
+            &conn,
+            ALIAS,
+            "{% if verbose %}set -x; {% endif %}ls {{ dir }}",
+        )?;
+        update_template_by_alias(&conn, ALIAS, true)?;
+        let command = retrieve_command_by_alias(&conn, ALIAS)?;
+        let text = crate::usage::usage_text(&command, &[]);
+        assert!(text.contains("smriti run init_alias [dir]\n"));
+        assert!(text.contains("smriti run init_alias <dir> --set verbose=...\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_placeholder_history() -> Result<()> {
+        let conn = db_connect()?;
+        insert_command(&conn, "true {host} {port=22}", ALIAS, INFO, SERVICE)?;
+        let command = retrieve_command_by_alias(&conn, ALIAS)?;
+        let history = |conn: &Connection| -> Result<Vec<(String, String)>> {
+            Ok(retrieve_placeholder_history(conn, command.id, None)?
+                .into_iter()
+                .map(|h| (h.name, h.value))
+                .collect())
+        };
+
+        // Nothing runs in dry-run mode, so nothing is remembered
+        let dry_run = RunCommand {
+            variables: strings(&["a.com"]),
+            dry_run: true,
+            ..run_args(ALIAS)
+        };
+        assert_eq!(crate::run_alias(&conn, dry_run, &mut vec![]), 0);
+        assert!(history(&conn)?.is_empty());
+
+        // Values passed as arguments are remembered, defaults are not
+        let run = RunCommand {
+            variables: strings(&["a.com"]),
+            ..run_args(ALIAS)
+        };
+        assert_eq!(crate::run_alias(&conn, run, &mut vec![]), 0);
+        assert_eq!(history(&conn)?, [("host".to_string(), "a.com".to_string())]);
+
+        let run = RunCommand {
+            variables: strings(&["b.com"]),
+            no_history: true,
+            ..run_args(ALIAS)
+        };
+        assert_eq!(crate::run_alias(&conn, run, &mut vec![]), 0);
+        assert_eq!(history(&conn)?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_logs() -> Result<()> {
+        let conn = db_connect()?;
+        insert_command(&conn, "echo out; echo err >&2", ALIAS, INFO, SERVICE)?;
+        insert_command(&conn, "echo try; exit 3", "flaky", INFO, SERVICE)?;
+        update_retries_by_alias(&conn, "flaky", 2)?;
+        update_retry_delay_by_alias(&conn, "flaky", Some(1))?;
+
+        // Runs are only stored with --capture, with both output streams
+        assert_eq!(crate::run_alias(&conn, run_args(ALIAS), &mut vec![]), 0);
+        assert!(retrieve_runs(&conn, None, 10)?.is_empty());
+        let capture = RunCommand {
+            capture: true,
+            ..run_args(ALIAS)
+        };
+        assert_eq!(crate::run_alias(&conn, capture, &mut vec![]), 0);
+        let runs = retrieve_runs(&conn, None, 10)?;
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].exit_code, Some(0));
+        let output = runs[0].output.clone().unwrap_or_default();
+        assert!(output.contains("out\n") && output.contains("err\n"));
+
+        // Every attempt of a failing command is stored, and its exit code is kept
+        let capture = RunCommand {
+            capture: true,
+            ..run_args("flaky")
+        };
+        assert_eq!(crate::run_alias(&conn, capture, &mut vec![]), 3);
+        let flaky = retrieve_command_by_alias(&conn, "flaky")?;
+        let attempts: Vec<(i64, Option<i32>)> = retrieve_runs(&conn, Some(flaky.id), 10)?
+            .into_iter()
+            .map(|run| (run.attempt, run.exit_code))
+            .collect();
+        assert_eq!(attempts, [(3, Some(3)), (2, Some(3)), (1, Some(3))]);
+
+        assert_eq!(
+            crate::run_alias(&conn, run_args("missing"), &mut vec![]),
+            exit_code::UNKNOWN_ALIAS
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let run =
+            crate::shell_executor::execute_command("exit 3".to_string(), &ExecOptions::default())
+                .unwrap();
+        assert_eq!(run.exit_code(), Some(3));
+
+        let run = crate::shell_executor::execute_command(
+            "kill -TERM $$".to_string(),
+            &ExecOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(run.exit_code(), Some(128 + 15));
+
+        // smriti's own errors outside `run` have codes too
+        let conn = db_connect().unwrap();
+        insert_command(&conn, COMMAND, ALIAS, INFO, SERVICE).unwrap();
+        let taken = insert_command(&conn, COMMAND, ALIAS, INFO, SERVICE).unwrap_err();
+        assert_eq!(crate::database_exit_code(&taken), exit_code::USAGE);
+        let missing = retrieve_command_by_alias(&conn, NEW_ALIAS).unwrap_err();
+        assert_eq!(
+            crate::database_exit_code(&missing),
+            exit_code::UNKNOWN_ALIAS
+        );
+        let broken = conn.execute("SELECT * FROM nothing", []).unwrap_err();
+        assert_eq!(crate::error_exit_code(&broken), exit_code::DATABASE);
+        assert_eq!(
+            crate::error_exit_code(&*Box::<dyn std::error::Error>::from("bad")),
+            exit_code::USAGE
+        );
+
+        // Failing to read values while resolving is a database error, not a usage one
+        update_command_by_alias(&conn, ALIAS, "echo {name}").unwrap();
+        conn.execute("DROP TABLE variables", []).unwrap();
+        let command = retrieve_command_by_alias(&conn, ALIAS).unwrap();
+        let run = crate::prepare_run(&conn, command, &run_args(ALIAS));
+        assert_eq!(run.err(), Some(exit_code::DATABASE));
+        conn.execute("DROP TABLE command_env", []).unwrap();
+        let args = RunCommand {
+            variables: strings(&["x"]),
+            ..run_args(ALIAS)
+        };
+        let command = retrieve_command_by_alias(&conn, ALIAS).unwrap();
+        assert_eq!(
+            crate::prepare_run(&conn, command, &args).err(),
+            Some(exit_code::DATABASE)
+        );
+    }
+
+    #[test]
+    fn test_interactive_commands() -> Result<()> {
+        use crate::shell_executor::{looks_interactive, Interactive};
+        use std::time::Duration;
+
+        assert_eq!(looks_interactive("ssh deploy@10.0.3.14"), Interactive::Yes);
+        assert_eq!(
+            looks_interactive("ssh -p 2222 -t deploy@db"),
+            Interactive::Yes
+        );
+        assert_eq!(
+            looks_interactive("sudo -E /usr/bin/vim /etc/hosts"),
+            Interactive::Yes
+        );
+        assert_eq!(looks_interactive("psql -h db app"), Interactive::Yes);
+        assert_eq!(looks_interactive("python3"), Interactive::Yes);
+        assert_eq!(looks_interactive("echo ssh"), Interactive::No);
+
+        // Given a script or command to run, they may not need the terminal at all
+        assert_eq!(
+            looks_interactive("python3 manage.py migrate"),
+            Interactive::Maybe
+        );
+        assert_eq!(looks_interactive("node build.js"), Interactive::Maybe);
+        assert_eq!(looks_interactive("ssh host uptime"), Interactive::Maybe);
+        assert_eq!(
+            looks_interactive("ssh -i ~/.ssh/key -p 22 host uptime"),
+            Interactive::Maybe
+        );
+        assert_eq!(looks_interactive("psql -c 'select 1'"), Interactive::Maybe);
+        assert_eq!(
+            looks_interactive("mysql --execute='show tables'"),
+            Interactive::Maybe
+        );
+        assert_eq!(
+            looks_interactive("psql -h db < schema.sql"),
+            Interactive::Maybe
+        );
+        assert_eq!(
+            looks_interactive("redis-cli -h cache ping"),
+            Interactive::Maybe
+        );
+
+        // Interactive commands keep the terminal: no capture and no timeout
+        let conn = db_connect()?;
+        insert_command(&conn, "psql -h db", ALIAS, INFO, SERVICE)?;
+        update_interactive_by_alias(&conn, ALIAS, true)?;
+        update_timeout_by_alias(&conn, ALIAS, Some(30_000))?;
+        let arg = RunCommand {
+            capture: true,
+            timeout: Some(Duration::from_secs(5)),
+            ..run_args(ALIAS)
+        };
+        let command = retrieve_command_by_alias(&conn, ALIAS)?;
+        let Ok(run) = crate::prepare_run(&conn, command, &arg) else {
+            panic!("Expected the command to resolve");
+        };
+        assert!(!run.options.capture);
+        assert_eq!(run.options.timeout, None);
+
+        let watch = RunCommand {
+            watch: strings(&["*.sql"]),
+            ..run_args(ALIAS)
+        };
+        assert_eq!(
+            crate::run_alias(&conn, watch, &mut vec![]),
+            exit_code::USAGE
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_interpreters() -> Result<()> {
+        use crate::shell_executor::{check_interpreter, execute_command};
+
+        assert!(check_interpreter("sh").is_ok());
+        assert!(check_interpreter("/bin/sh -e").is_ok());
+        assert!(check_interpreter("no-such-shell").is_err());
+
+        let options = ExecOptions {
+            capture: true,
+            interpreter: Some("perl".to_string()),
+            ..Default::default()
+        };
+        if check_interpreter("perl").is_ok() {
+            let run = execute_command("print 6 * 7".to_string(), &options).unwrap();
+            assert_eq!(run.output.as_deref(), Some("42"));
+        }
+
+        // The command's own interpreter wins over the default shell setting
+        let conn = db_connect()?;
+        insert_command(&conn, COMMAND, ALIAS, INFO, SERVICE)?;
+        set_setting(&conn, "shell", "zsh")?;
+        let interpreter = |conn: &Connection| -> Result<Option<String>> {
+            let command = retrieve_command_by_alias(conn, ALIAS)?;
+            match crate::prepare_run(conn, command, &run_args(ALIAS)) {
+                Ok(run) => Ok(run.options.interpreter),
+                Err(code) => panic!("Expected the command to resolve, got {}", code),
+            }
+        };
+        assert_eq!(interpreter(&conn)?.as_deref(), Some("zsh"));
+        update_shell_by_alias(&conn, ALIAS, Some("bash"))?;
+        assert_eq!(interpreter(&conn)?.as_deref(), Some("bash"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_working_directory() -> Result<()> {
+        use crate::shell_executor::{execute_command, expand_directory};
+        use std::path::PathBuf;
+
+        std::env::set_var("SMRITI_TEST_DIR", "/tmp");
+        assert_eq!(
+            expand_directory("${SMRITI_TEST_DIR}/../tmp").unwrap(),
+            PathBuf::from("/tmp/../tmp")
+        );
+        assert!(expand_directory("~").is_ok());
+        assert!(expand_directory("$SMRITI_UNSET_VARIABLE/x").is_err());
+        assert!(expand_directory("/no/such/directory").is_err());
+
+        let options = ExecOptions {
+            capture: true,
+            cwd: Some(expand_directory("/tmp").unwrap()),
+            ..Default::default()
+        };
+        let run = execute_command("pwd".to_string(), &options).unwrap();
+        assert_eq!(run.output.as_deref(), Some("/tmp\n"));
+
+        // --cwd wins over the saved directory, and {@cwd} follows it
+        let conn = db_connect()?;
+        insert_command(&conn, "ls {@cwd}", ALIAS, INFO, SERVICE)?;
+        update_cwd_by_alias(&conn, ALIAS, Some("$SMRITI_TEST_DIR"))?;
+        let prepare = |arg: &RunCommand| {
+            let command = retrieve_command_by_alias(&conn, ALIAS).unwrap();
+            crate::prepare_run(&conn, command, arg).map(|run| (run.line, run.options.cwd))
+        };
+        assert_eq!(
+            prepare(&run_args(ALIAS)),
+            Ok(("ls /tmp".to_string(), Some(PathBuf::from("/tmp"))))
+        );
+        let arg = RunCommand {
+            cwd: Some("/".to_string()),
+            ..run_args(ALIAS)
+        };
+        assert_eq!(
+            prepare(&arg),
+            Ok(("ls /".to_string(), Some(PathBuf::from("/"))))
+        );
+
+        update_cwd_by_alias(&conn, ALIAS, Some("/no/such/directory"))?;
+        assert_eq!(prepare(&run_args(ALIAS)), Err(exit_code::USAGE));
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_env() -> Result<()> {
+        use crate::shell_executor::execute_command;
+
+        let conn = db_connect()?;
+        insert_command(
+            &conn,
+            "kubectl get pods -n {namespace}",
+            ALIAS,
+            INFO,
+            SERVICE,
+        )?;
+        let command = retrieve_command_by_alias(&conn, ALIAS)?;
+        set_command_env(&conn, command.id, "KUBECONFIG", "~/.kube/{cluster}")?;
+        set_command_env(&conn, command.id, "NAMESPACE", "{namespace}")?;
+        set_command_env(&conn, command.id, "AWS_PROFILE", "prod")?;
+
+        // Values reuse the command's placeholders and may add their own
+        let named = vec![("cluster".to_string(), "my cluster".to_string())];
+        assert!(crate::resolve_command(&conn, &command, strings(&["web"]), &[], None).is_err());
+        let resolved =
+            crate::resolve_command(&conn, &command, strings(&["web"]), &named, None).unwrap();
+        assert_eq!(resolved.command, "kubectl get pods -n web");
+        assert!(resolved
+            .env
+            .contains(&("KUBECONFIG".to_string(), "~/.kube/my cluster".to_string())));
+        assert!(resolved
+            .env
+            .contains(&("NAMESPACE".to_string(), "web".to_string())));
+
+        // --env replaces the command's own value
+        let arg = RunCommand {
+            variables: strings(&["web"]),
+            set: named,
+            env: vec![("AWS_PROFILE".to_string(), "dev".to_string())],
+            ..run_args(ALIAS)
+        };
+        let Ok(run) = crate::prepare_run(&conn, command, &arg) else {
+            panic!("Expected the command to resolve");
+        };
+        let profiles: Vec<&str> = run
+            .options
+            .env
+            .iter()
+            .filter(|(name, _)| name == "AWS_PROFILE")
+            .map(|(_, value)| value.as_str())
+            .collect();
+        assert_eq!(profiles, ["dev"]);
+
+        let options = ExecOptions {
+            capture: true,
+            env: vec![("SMRITI_GREETING".to_string(), "hi".to_string())],
+            clear_env: true,
+            ..Default::default()
+        };
+        let run =
+            execute_command("echo $SMRITI_GREETING ${HOME:-none}".to_string(), &options).unwrap();
+        assert_eq!(run.output.as_deref(), Some("hi none\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_saves_all_or_nothing() -> Result<()> {
+        let conn = db_connect()?;
+        let add = |args: &[&str]| {
+            let mut argv = vec!["smriti", "add"];
+            argv.extend_from_slice(args);
+            match SmritiCli::parse_from(argv).command {
+                CliArgs::Add(add) => add,
+                _ => panic!("Expected Add command"),
+            }
+        };
+
+        let args = add(&[
+            "-c",
+            "ls {dir}",
+            "-a",
+            ALIAS,
+            "-s",
+            SERVICE,
+            "--env",
+            "A=1",
+            "--interactive",
+        ]);
+        let command = crate::save_added_command(&conn, &args, &args.command, false, true)?;
+        assert!(retrieve_command_by_alias(&conn, ALIAS)?.interactive);
+        assert_eq!(retrieve_command_env(&conn, command.id)?.len(), 1);
+
+        // A failed write of the environment leaves no command behind
+        conn.execute("DROP TABLE command_env", [])?;
+        let args = add(&["-c", "ls", "-a", NEW_ALIAS, "-s", SERVICE, "--env", "A=1"]);
+        assert!(crate::save_added_command(&conn, &args, &args.command, false, false).is_err());
+        assert!(retrieve_command_by_alias(&conn, NEW_ALIAS).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_saves_all_or_nothing() -> Result<()> {
+        let conn = db_connect()?;
+        insert_command(&conn, COMMAND, ALIAS, INFO, SERVICE)?;
+        let update = |args: &[&str]| {
+            let mut argv = vec!["smriti", "update", "-a", ALIAS];
+            argv.extend_from_slice(args);
+            match SmritiCli::parse_from(argv).command {
+                CliArgs::Update(update) => update,
+                _ => panic!("Expected Update command"),
+            }
+        };
+
+        let saved = crate::save_updated_command(&conn, &update(&["--confirm", "true"]), None);
+        assert_eq!(saved.unwrap(), ["Confirmation enabled for init_alias"]);
+
+        // A failed write of the environment leaves the other fields as they were
+        conn.execute("DROP TABLE command_env", [])?;
+        let args = update(&["-c", "echo world", "--template", "true", "--env", "A=1"]);
+        assert!(crate::save_updated_command(&conn, &args, Some(NEW_ALIAS)).is_err());
+        let command = retrieve_command_by_alias(&conn, ALIAS)?;
+        assert_eq!(command.command, COMMAND);
+        assert!(!command.template);
+        assert!(command.confirm);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dry_run_and_print_flags() {
+        let matches = SmritiCli::parse_from(["smriti", "run", ALIAS, "--dry-run"]);
+        if let CliArgs::Run(run) = matches.command {
+            assert!(run.dry_run);
+            assert!(!run.print);
+        } else {
+            panic!("Expected Run command");
+        }
+
+        assert!(
+            SmritiCli::try_parse_from(["smriti", "run", ALIAS, "--print", "--dry-run"]).is_err()
+        );
+        assert!(
+            SmritiCli::try_parse_from(["smriti", "run", ALIAS, "--print", "--capture"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_danger_patterns() -> Result<()> {
+        use crate::config::{check_setting, is_dangerous, DANGER_PATTERNS};
+
+        let conn = db_connect()?;
+        assert!(is_dangerous(&conn, "rm -rf {dir}"));
+        assert!(is_dangerous(&conn, "kubectl delete pod {pod}"));
+        assert!(is_dangerous(&conn, "psql -c 'DROP TABLE users'"));
+        assert!(is_dangerous(&conn, "git push origin main --force"));
+        assert!(!is_dangerous(&conn, "rm notes.txt"));
+        assert!(!is_dangerous(&conn, "kubectl get pods"));
+
+        assert!(check_setting(DANGER_PATTERNS, "helm (").is_err());
+        assert!(check_setting("no_such_setting", "x").is_err());
+        set_setting(&conn, DANGER_PATTERNS, "helm uninstall")?;
+        assert!(is_dangerous(&conn, "helm uninstall api"));
+        assert!(!is_dangerous(&conn, "rm -rf build"));
+
+        // Without a terminal to confirm on, only --yes lets the command run
+        insert_command(&conn, COMMAND, ALIAS, INFO, SERVICE)?;
+        update_confirm_by_alias(&conn, ALIAS, true)?;
+        assert_eq!(
+            crate::run_alias(&conn, run_args(ALIAS), &mut vec![]),
+            exit_code::NOT_CONFIRMED
+        );
+        let yes = RunCommand {
+            yes: true,
+            ..run_args(ALIAS)
+        };
+        assert_eq!(crate::run_alias(&conn, yes, &mut vec![]), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_timeouts_and_retries() -> Result<()> {
+        use crate::shell_executor::execute_command;
+        use cli::{format_duration, parse_duration};
+        use std::time::{Duration, Instant};
+
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert!(parse_duration("5 minutes").is_err());
+        assert!(parse_duration("9999999999999999h").is_err());
+        assert!(parse_duration("18446744073709551615s").is_err());
+        assert_eq!(format_duration(Duration::from_secs(90)), "1m30s");
+        assert_eq!(format_duration(Duration::from_millis(1500)), "1500ms");
+
+        // The background sleep keeps the output open unless the whole group is stopped
+        let options = ExecOptions {
+            capture: true,
+            timeout: Some(Duration::from_millis(300)),
+            ..Default::default()
+        };
+        let started = Instant::now();
+        let run =
+            execute_command("echo started; sleep 10 & sleep 10".to_string(), &options).unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(run.timed_out);
+        assert_eq!(run.exit_code(), Some(crate::exit_code::TIMEOUT));
+        assert!(run.output.unwrap_or_default().starts_with("started\n"));
+
+        // Flags given to `run` win over the saved settings, and 0 turns the timeout off
+        let conn = db_connect()?;
+        insert_command(&conn, COMMAND, ALIAS, INFO, SERVICE)?;
+        update_timeout_by_alias(&conn, ALIAS, Some(30_000))?;
+        update_retries_by_alias(&conn, ALIAS, 3)?;
+        let prepare = |arg: &RunCommand| {
+            let command = retrieve_command_by_alias(&conn, ALIAS).unwrap();
+            match crate::prepare_run(&conn, command, arg) {
+                Ok(run) => (run.options.timeout, run.retry.retries, run.retry.delay),
+                Err(code) => panic!("Expected the command to resolve, got {}", code),
+            }
+        };
+        assert_eq!(
+            prepare(&run_args(ALIAS)),
+            (Some(Duration::from_secs(30)), 3, Duration::from_secs(1))
+        );
+        let arg = RunCommand {
+            timeout: Some(Duration::ZERO),
+            retries: Some(1),
+            retry_delay: Some(Duration::from_millis(10)),
+            ..run_args(ALIAS)
+        };
+        assert_eq!(prepare(&arg), (None, 1, Duration::from_millis(10)));
+
+        // Each attempt is stored even without --capture, only without its output
+        update_command_by_alias(&conn, ALIAS, "echo failing; exit 5")?;
+        assert_eq!(crate::run_alias(&conn, arg, &mut vec![]), 5);
+        let runs = retrieve_runs(&conn, None, 10)?;
+        assert_eq!(runs.len(), 2);
+        assert!(runs
+            .iter()
+            .all(|run| run.exit_code == Some(5) && run.output.is_none()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_service_runs() -> Result<()> {
+        use crate::shell_executor::execute_command;
+
+        let matches =
+            SmritiCli::parse_from(["smriti", "run", "--service", SERVICE, "--parallel", "4"]);
+        if let CliArgs::Run(run) = matches.command {
+            assert_eq!(run.service.as_deref(), Some(SERVICE));
+            assert_eq!(run.parallel, Some(4));
+            assert!(run.alias.is_none());
+        } else {
+            panic!("Expected Run command");
+        }
+        assert!(SmritiCli::try_parse_from(["smriti", "run", ALIAS, "--parallel", "2"]).is_err());
+        assert!(SmritiCli::try_parse_from(["smriti", "run", "--service", SERVICE, ALIAS]).is_err());
+        assert!(SmritiCli::try_parse_from([
+            "smriti",
+            "run",
+            "--service",
+            SERVICE,
+            "--parallel",
+            "0"
+        ])
+        .is_err());
+
+        // Prefixed lines go to the terminal, the stored output stays as the command wrote it
+        let options = ExecOptions {
+            capture: true,
+            prefix: Some("api | ".to_string()),
+            ..Default::default()
+        };
+        let run = execute_command("echo one; printf two".to_string(), &options).unwrap();
+        assert_eq!(run.output.as_deref(), Some("one\ntwo"));
+
+        // Interactive commands and workflows are skipped, the rest run side by side
+        let conn = db_connect()?;
+        insert_command(&conn, "echo first", "first", INFO, SERVICE)?;
+        insert_command(&conn, "exit 4", "failing", INFO, SERVICE)?;
+        insert_command(&conn, "echo shell", "console", INFO, SERVICE)?;
+        update_interactive_by_alias(&conn, "console", true)?;
+        insert_command(&conn, "first; failing", "both", INFO, SERVICE)?;
+        update_workflow_by_alias(&conn, "both", true)?;
+
+        let arg = RunCommand {
+            service: Some(SERVICE.to_string()),
+            parallel: Some(2),
+            capture: true,
+            ..Default::default()
+        };
+        assert_eq!(crate::run_service(&conn, SERVICE, arg), 4);
+        let mut ran: Vec<(String, Option<i32>)> = retrieve_runs(&conn, None, 10)?
+            .into_iter()
+            .map(|run| (run.alias, run.exit_code))
+            .collect();
+        ran.sort();
+        assert_eq!(
+            ran,
+            [
+                ("failing".to_string(), Some(4)),
+                ("first".to_string(), Some(0))
+            ]
+        );
+        assert_eq!(
+            crate::run_service(&conn, "missing", RunCommand::default()),
+            exit_code::UNKNOWN_ALIAS
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_scripts() -> Result<()> {
+        use crate::shell_executor::{execute_command, shebang};
+
+        assert_eq!(
+            shebang("#!/usr/bin/env bash\necho"),
+            Some("/usr/bin/env bash")
+        );
+        assert_eq!(shebang("echo\n#!/bin/sh"), None);
+        assert!(SmritiCli::try_parse_from(["smriti", "add", "--edit"]).is_ok());
+        assert!(SmritiCli::try_parse_from(["smriti", "add", "-a", ALIAS, "-s", SERVICE]).is_err());
+        assert!(SmritiCli::try_parse_from([
+            "smriti", "update", "-a", ALIAS, "-c", COMMAND, "--edit"
+        ])
+        .is_err());
+
+        // Quotes and new lines need no escaping, and $0 is the script file
+        let options = ExecOptions {
+            capture: true,
+            script: true,
+            ..Default::default()
+        };
+        let script = "#!/bin/sh\nset -e\necho \"it's $0\" | grep -q smriti-\necho 'two\nlines'\n";
+        let run = execute_command(script.to_string(), &options).unwrap();
+        assert_eq!(run.output.as_deref(), Some("two\nlines\n"));
+
+        // Only the user can read the script file
+        let run = execute_command("#!/bin/sh\nstat -c %a \"$0\"\n".to_string(), &options).unwrap();
+        assert_eq!(run.output.as_deref(), Some("600\n"));
+
+        // Saved scripts run from a file, so `set -e` stops at the first failure
+        let conn = db_connect()?;
+        insert_command(
+            &conn,
+            "set -e\nfalse\necho unreachable\n",
+            ALIAS,
+            INFO,
+            SERVICE,
+        )?;
+        update_script_by_alias(&conn, ALIAS, true)?;
+        let capture = RunCommand {
+            capture: true,
+            ..run_args(ALIAS)
+        };
+        assert_eq!(crate::run_alias(&conn, capture, &mut vec![]), 1);
+        assert_eq!(
+            retrieve_runs(&conn, None, 1)?[0].output.as_deref(),
+            Some("")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_watch_flags() {
+        let parse = |args: &[&str]| SmritiCli::try_parse_from([&["smriti", "run"], args].concat());
+        assert!(parse(&[
+            ALIAS,
+            "--watch",
+            "src/**/*.rs",
+            "--watch",
+            "*.toml",
+            "--clear"
+        ])
+        .is_ok());
+        assert!(parse(&[ALIAS, "--clear"]).is_err());
+        assert!(parse(&[ALIAS, "--watch", "*.rs", "--dry-run"]).is_err());
+        assert!(parse(&["--service", SERVICE, "--watch", "*.rs"]).is_err());
+    }
+
+    #[test]
+    fn test_schedules() {
+        use crate::cron::Cron;
+        use crate::due_times;
+
+        let parse =
+            |args: &[&str]| SmritiCli::try_parse_from([&["smriti", "schedule"], args].concat());
+        assert!(parse(&["add", ALIAS, "--cron", "*/15 * * * *", "--missed", "all"]).is_ok());
+        assert!(parse(&["add", ALIAS, "--cron", "@daily", "--missed", "later"]).is_err());
+        assert!(parse(&["add", ALIAS]).is_err());
+
+        // Every quarter hour in the last hour, up to and including now
+        let cron = Cron::parse("*/15 * * * *").unwrap();
+        let now = chrono::Local::now().timestamp() / 900 * 900;
+        assert_eq!(due_times(&cron, now - 3600, now).len(), 4);
+        assert_eq!(due_times(&cron, now, now).len(), 0);
+        assert_eq!(
+            due_times(&Cron::parse("* * * * *").unwrap(), now - 86400, now).len(),
+            100
+        );
+        // The next run is only due at the next quarter hour
+        assert_eq!(due_times(&cron, now, now + 899).len(), 0);
+    }
 }
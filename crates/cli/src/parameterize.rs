@@ -0,0 +1,125 @@
+use regex::Regex;
+use std::collections::HashSet;
+
+/// A literal in a concrete command that could become a placeholder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Literal {
+    pub start: usize,
+    pub end: usize,
+    pub value: String,
+    /// Suggested placeholder name, e.g. `ip` or `port2`.
+    pub name: String,
+}
+
+// Patterns in the order they claim text; the literal is capture group 1.
+fn patterns() -> Vec<(&'static str, Regex)> {
+    [
+        ("url", r"\b([a-z][a-z0-9+.-]*://[^\s'\x22{}]+)"),
+        ("ip", r"\b((?:\d{1,3}\.){3}\d{1,3})\b"),
+        ("port", r"(?:^|\s)(?:-p|-P|--port)[\s=](\d{1,5})\b"),
+        // After a host name or IP, so clock times such as 12:30 are left alone
+        (
+            "port",
+            r"(?:^|[^a-zA-Z0-9.-])(?:(?:\d{1,3}\.){3}\d{1,3}|[a-zA-Z][a-zA-Z0-9.-]*):(\d{2,5})\b",
+        ),
+        ("path", r"(?:^|[\s=])((?:~|\.{1,2})?/[^\s'\x22{};|&()<>]+)"),
+    ]
+    .into_iter()
+    .map(|(name, pattern)| (name, Regex::new(pattern).unwrap()))
+    .collect()
+}
+
+fn is_ip(value: &str) -> bool {
+    value.split('.').all(|octet| octet.parse::<u8>().is_ok())
+}
+
+/// Finds IP addresses, ports, URLs and file paths in a concrete command.
+/// The same value always gets the same name.
+pub fn detect_literals(command: &str) -> Vec<Literal> {
+    let mut literals: Vec<Literal> = Vec::new();
+
+    for (kind, pattern) in patterns() {
+        for captures in pattern.captures_iter(command) {
+            let m = captures.get(1).unwrap();
+            let overlaps = literals
+                .iter()
+                .any(|l| m.start() < l.end && l.start < m.end());
+            if overlaps || (kind == "ip" && !is_ip(m.as_str())) {
+                continue;
+            }
+
+            let name = match literals.iter().find(|l| l.value == m.as_str()) {
+                Some(same) => same.name.clone(),
+                None => {
+                    let seen: HashSet<&str> = literals
+                        .iter()
+                        .filter(|l| l.name.trim_end_matches(char::is_numeric) == kind)
+                        .map(|l| l.value.as_str())
+                        .collect();
+                    match seen.len() {
+                        0 => kind.to_string(),
+                        n => format!("{}{}", kind, n + 1),
+                    }
+                }
+            };
+
+            literals.push(Literal {
+                start: m.start(),
+                end: m.end(),
+                value: m.as_str().to_string(),
+                name,
+            });
+        }
+    }
+
+    literals.sort_by_key(|l| l.start);
+    literals
+}
+
+// Values that rely on the shell to expand them must not be quoted.
+fn needs_raw(value: &str) -> bool {
+    !value.chars().all(|c| {
+        c.is_ascii_alphanumeric()
+            || matches!(c, '_' | '-' | '.' | '/' | ':' | '@' | '%' | '+' | ',' | '=')
+    })
+}
+
+/// Replaces each literal with a placeholder whose default is the original
+/// value. `name_for` picks the name of every distinct value; `None` keeps the
+/// literal as it is.
+pub fn parameterize<F>(command: &str, literals: &[Literal], mut name_for: F) -> String
+where
+    F: FnMut(&Literal) -> Option<String>,
+{
+    let mut names: Vec<(String, Option<String>)> = Vec::new();
+    let mut result = String::new();
+    let mut last = 0;
+
+    for literal in literals {
+        let name = match names.iter().find(|(value, _)| *value == literal.value) {
+            Some((_, name)) => name.clone(),
+            None => {
+                let name = name_for(literal);
+                names.push((literal.value.clone(), name.clone()));
+                name
+            }
+        };
+
+        result.push_str(&command[last..literal.start]);
+        match name {
+            Some(name) => {
+                let raw = if needs_raw(&literal.value) {
+                    ":raw"
+                } else {
+                    ""
+                };
+                result.push_str(&format!("{{{}{}={}}}", name, raw, literal.value));
+            }
+            None => result.push_str(&literal.value),
+        }
+        last = literal.end;
+    }
+
+    result.push_str(&command[last..]);
+    result
+}
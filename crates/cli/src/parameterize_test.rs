@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod tests {
+    use crate::parameterize::*;
+    use crate::placeholder::replace_placeholder;
+
+    fn suggested(command: &str) -> String {
+        let literals = detect_literals(command);
+        parameterize(command, &literals, |literal| Some(literal.name.clone()))
+    }
+
+    #[test]
+    fn test_detect_literals() {
+        let names: Vec<(String, String)> = detect_literals("ssh deploy@10.0.3.14 -p 2222")
+            .into_iter()
+            .map(|l| (l.name, l.value))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("ip".to_string(), "10.0.3.14".to_string()),
+                ("port".to_string(), "2222".to_string()),
+            ]
+        );
+
+        assert!(detect_literals("echo 300.1.1.1 ${HOME}/x").is_empty());
+    }
+
+    #[test]
+    fn test_repeated_values_keep_their_names() {
+        assert_eq!(
+            suggested("ping 10.0.0.1; ping 10.0.0.2; ping 10.0.0.1; ping 10.0.0.3"),
+            "ping {ip=10.0.0.1}; ping {ip2=10.0.0.2}; ping {ip=10.0.0.1}; ping {ip3=10.0.0.3}"
+        );
+    }
+
+    #[test]
+    fn test_ports_need_a_host() {
+        assert_eq!(
+            suggested("echo at 12:30 2024-01-01T08:15 && psql -h db.local:5432 localhost:8080"),
+            "echo at 12:30 2024-01-01T08:15 && psql -h db.local:{port=5432} localhost:{port2=8080}"
+        );
+    }
+
+    #[test]
+    fn test_parameterize() {
+        assert_eq!(
+            suggested("ssh deploy@10.0.3.14 -p 2222"),
+            "ssh deploy@{ip=10.0.3.14} -p {port=2222}"
+        );
+        assert_eq!(
+            suggested("curl https://10.0.0.1:8443/health -o ./out.json"),
+            "curl {url=https://10.0.0.1:8443/health} -o {path=./out.json}"
+        );
+        assert_eq!(
+            suggested("rsync ~/notes 10.0.0.1:/srv 10.0.0.2:/srv 10.0.0.1:/tmp"),
+            "rsync {path:raw=~/notes} {ip=10.0.0.1}:/srv {ip2=10.0.0.2}:/srv {ip=10.0.0.1}:/tmp"
+        );
+
+        let command = "scp ./a.txt 10.0.0.5:/tmp";
+        let literals = detect_literals(command);
+        let kept = parameterize(command, &literals, |literal| match literal.name.as_str() {
+            "ip" => Some("host".to_string()),
+            _ => None,
+        });
+        assert_eq!(kept, "scp ./a.txt {host=10.0.0.5}:/tmp");
+        assert_eq!(
+            replace_placeholder(&kept, vec!["db1".to_string()]).unwrap(),
+            "scp ./a.txt db1:/tmp"
+        );
+    }
+}
@@ -0,0 +1,620 @@
+use crate::functions;
+use regex::Regex;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+/// Shell quoting context a placeholder was found in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quote {
+    None,
+    Single,
+    Double,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placeholder {
+    pub name: String,
+    pub quote: Quote,
+    /// `{name:raw}` splices the value in without shell quoting.
+    pub raw: bool,
+    /// `{name=value}` is used when nothing else provides a value.
+    pub default: Option<String>,
+    /// `{name...}` takes all remaining arguments; holds the minimum count.
+    pub variadic: Option<usize>,
+}
+
+impl Placeholder {
+    /// `{@name}` placeholders are evaluated by smriti itself.
+    pub fn is_function(&self) -> bool {
+        self.name.starts_with('@')
+    }
+
+    /// Whether the placeholder takes its value from positional arguments.
+    pub fn is_positional(&self) -> bool {
+        !self.name.starts_with("env.") && !self.is_function()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+fn placeholder_regex() -> Regex {
+    // body of a {placeholder}: name or @function, variadic marker and minimum, raw marker, default
+    Regex::new(
+        r"^((?:env\.)?[a-zA-Z_][a-zA-Z0-9_]*|@[a-z_]+(?::[^{}]*)?)(\.\.\.(\d*))?(:raw)?(?:=([^{}]*))?$",
+    )
+    .unwrap()
+}
+
+/// The language a command is written in, which decides how values are quoted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// `sh`, `bash`, `zsh` and the other POSIX shells.
+    Posix,
+    Fish,
+    /// Code for another interpreter, such as `python3` or `node`. Values are
+    /// only substituted when they cannot change the code around them.
+    Code,
+}
+
+impl Dialect {
+    /// The dialect of an interpreter such as `bash`, `/usr/bin/env fish` or
+    /// `python3 -u`, or of `sh` when there is none.
+    pub fn of(interpreter: Option<&str>) -> Dialect {
+        let mut words = interpreter
+            .unwrap_or("sh")
+            .split_whitespace()
+            .map(|word| word.rsplit('/').next().unwrap_or(word));
+        let mut program = words.next().unwrap_or("sh");
+        if program == "env" {
+            program = words
+                .find(|word| !word.starts_with('-') && !word.contains('='))
+                .unwrap_or("sh");
+        }
+        match program {
+            "sh" | "bash" | "zsh" | "dash" | "ksh" | "mksh" | "ash" | "yash" | "busybox" => {
+                Dialect::Posix
+            }
+            "fish" => Dialect::Fish,
+            _ => Dialect::Code,
+        }
+    }
+}
+
+/// Quotes `value` for the given context in `dialect`, or returns `None` when
+/// it cannot be substituted there without changing the code around it.
+pub fn quote_for(value: &str, quote: Quote, dialect: Dialect) -> Option<String> {
+    match dialect {
+        Dialect::Posix => Some(quote_value(value, quote)),
+        Dialect::Fish => Some(quote_fish(value, quote)),
+        Dialect::Code => {
+            // Outside strings only numbers and names, inside them nothing that
+            // ends the string, escapes or interpolates
+            let inert = match quote {
+                Quote::None => {
+                    !value.is_empty()
+                        && value
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || "_.+-".contains(c))
+                }
+                Quote::Single | Quote::Double => !value
+                    .chars()
+                    .any(|c| c.is_control() || "'\"\\`$@#{}".contains(c)),
+            };
+            inert.then(|| value.to_string())
+        }
+    }
+}
+
+// fish only knows `\'` and `\\` inside single quotes, and has no backticks.
+fn quote_fish(value: &str, quote: Quote) -> String {
+    let escape = |value: &str, special: &str| {
+        let mut escaped = String::new();
+        for c in value.chars() {
+            if special.contains(c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    };
+    match quote {
+        Quote::None => {
+            let safe = |c: char| c.is_ascii_alphanumeric() || "_@+=:,./-".contains(c);
+            if !value.is_empty() && value.chars().all(safe) {
+                value.to_string()
+            } else {
+                format!("'{}'", escape(value, "\\'"))
+            }
+        }
+        Quote::Single => escape(value, "\\'"),
+        Quote::Double => escape(value, "\\\"$"),
+    }
+}
+
+/// Quotes `value` so a POSIX shell sees it as a single literal word in the given context.
+pub fn quote_value(value: &str, quote: Quote) -> String {
+    match quote {
+        Quote::None => {
+            let safe = |c: char| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c);
+            if !value.is_empty() && value.chars().all(safe) {
+                value.to_string()
+            } else {
+                format!("'{}'", value.replace('\'', r"'\''"))
+            }
+        }
+        Quote::Single => value.replace('\'', r"'\''"),
+        Quote::Double => {
+            let mut escaped = String::new();
+            for c in value.chars() {
+                if matches!(c, '\\' | '"' | '$' | '`') {
+                    escaped.push('\\');
+                }
+                escaped.push(c);
+            }
+            escaped
+        }
+    }
+}
+
+// Returns the index of the `}` closing the brace opened at `start`.
+fn closing_brace(chars: &[char], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in chars.iter().enumerate().skip(start) {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Reads a `{body}` starting at `start` where body has no nested braces.
+fn brace_body(chars: &[char], start: usize) -> Option<String> {
+    let mut body = String::new();
+    for c in chars.iter().skip(start + 1) {
+        match c {
+            '}' => return Some(body),
+            '{' => return None,
+            c => body.push(*c),
+        }
+    }
+    None
+}
+
+/// Splits a saved command into literal text and placeholders.
+///
+/// `${...}` is left for the shell, `{{name}}` is an escaped literal `{name}`,
+/// and any other brace that does not wrap a placeholder name is kept as is.
+/// Quotes inside `$(...)` and backticks are tracked apart from the ones around them.
+pub fn parse_command(command: &str) -> Vec<Segment> {
+    let re = placeholder_regex();
+    let chars: Vec<char> = command.chars().collect();
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut quote = Quote::None;
+    // Command substitutions start their own quoting: the quote they were opened
+    // in, the character closing them and how many `(` are open inside them
+    let mut substitutions: Vec<(Quote, char, usize)> = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' if quote != Quote::Single => {
+                literal.push(c);
+                if let Some(next) = chars.get(i + 1) {
+                    literal.push(*next);
+                    i += 1;
+                }
+            }
+            '\'' if quote != Quote::Double => {
+                quote = if quote == Quote::Single {
+                    Quote::None
+                } else {
+                    Quote::Single
+                };
+                literal.push(c);
+            }
+            '"' if quote != Quote::Single => {
+                quote = if quote == Quote::Double {
+                    Quote::None
+                } else {
+                    Quote::Double
+                };
+                literal.push(c);
+            }
+            '$' if quote != Quote::Single && chars.get(i + 1) == Some(&'(') => {
+                substitutions.push((quote, ')', 0));
+                quote = Quote::None;
+                literal.push_str("$(");
+                i += 1;
+            }
+            '`' if quote != Quote::Single => {
+                match substitutions.last() {
+                    Some(&(outer, '`', _)) if quote == Quote::None => {
+                        substitutions.pop();
+                        quote = outer;
+                    }
+                    _ => {
+                        substitutions.push((quote, '`', 0));
+                        quote = Quote::None;
+                    }
+                }
+                literal.push(c);
+            }
+            '(' if quote == Quote::None => {
+                if let Some((_, ')', depth)) = substitutions.last_mut() {
+                    *depth += 1;
+                }
+                literal.push(c);
+            }
+            ')' if quote == Quote::None => {
+                match substitutions.last_mut() {
+                    Some((_, ')', depth)) if *depth > 0 => *depth -= 1,
+                    Some(&mut (outer, ')', _)) => {
+                        substitutions.pop();
+                        quote = outer;
+                    }
+                    _ => {}
+                }
+                literal.push(c);
+            }
+            '$' if chars.get(i + 1) == Some(&'{') => {
+                // shell parameter expansion, copied through untouched
+                let end = closing_brace(&chars, i + 1).unwrap_or(chars.len() - 1);
+                literal.extend(&chars[i..=end]);
+                i = end;
+            }
+            '{' => {
+                let escaped = if chars.get(i + 1) == Some(&'{') {
+                    brace_body(&chars, i + 1)
+                        .filter(|body| re.is_match(body))
+                        .filter(|body| chars.get(i + body.chars().count() + 3) == Some(&'}'))
+                } else {
+                    None
+                };
+
+                if let Some(body) = escaped {
+                    // {{name}} -> {name}
+                    literal.push_str(&format!("{{{}}}", body));
+                    i += body.chars().count() + 3;
+                } else if let Some(body) = brace_body(&chars, i).filter(|body| re.is_match(body)) {
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    i += body.chars().count() + 1;
+                    let cap = re.captures(&body).unwrap();
+                    segments.push(Segment::Placeholder(Placeholder {
+                        name: cap[1].to_string(),
+                        quote,
+                        raw: cap.get(4).is_some(),
+                        default: cap.get(5).map(|m| m.as_str().to_string()),
+                        variadic: cap.get(2).map(|_| cap[3].parse::<usize>().unwrap_or(0)),
+                    }));
+                } else {
+                    literal.push(c);
+                }
+            }
+            _ => literal.push(c),
+        }
+        i += 1;
+    }
+
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    segments
+}
+
+/// Unique placeholders in the order they first appear.
+pub fn placeholders(command: &str) -> Vec<Placeholder> {
+    let mut placeholders: Vec<Placeholder> = Vec::new();
+    for segment in parse_command(command) {
+        if let Segment::Placeholder(placeholder) = segment {
+            match placeholders.iter_mut().find(|p| p.name == placeholder.name) {
+                Some(existing) => {
+                    if existing.default.is_none() {
+                        existing.default = placeholder.default;
+                    }
+                }
+                None => placeholders.push(placeholder),
+            }
+        }
+    }
+    placeholders
+}
+
+// A variadic placeholder followed by another positional one.
+fn misplaced_variadic(placeholders: &[Placeholder]) -> Option<&Placeholder> {
+    let positional: Vec<&Placeholder> = placeholders.iter().filter(|p| p.is_positional()).collect();
+    let position = positional.iter().position(|p| p.variadic.is_some())?;
+    if position != positional.len() - 1 {
+        Some(positional[position])
+    } else {
+        None
+    }
+}
+
+/// Warnings for braces whose meaning may differ from what the user intended.
+pub fn ambiguous_braces(command: &str) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    for segment in parse_command(command) {
+        if let Segment::Placeholder(placeholder) = segment {
+            if placeholder.quote == Quote::Single {
+                let warning = format!(
+                    "{{{0}}} is inside single quotes and will still be replaced; write {{{{{0}}}}} to keep it literal",
+                    placeholder.name
+                );
+                if !warnings.contains(&warning) {
+                    warnings.push(warning);
+                }
+            }
+        }
+    }
+
+    if let Some(variadic) = misplaced_variadic(&placeholders(command)) {
+        warnings.push(format!(
+            "{{{}...}} takes all remaining arguments and must be the last placeholder",
+            variadic.name
+        ));
+    }
+
+    let re = placeholder_regex();
+    let escaped = Regex::new(r"\{\{([^{}]*)\}\}").unwrap();
+    for cap in escaped
+        .captures_iter(command)
+        .filter(|cap| re.is_match(&cap[1]))
+    {
+        let warning = format!(
+            "{} is an escaped literal and will be passed to the shell as {{{}}}",
+            &cap[0], &cap[1]
+        );
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    }
+
+    warnings
+}
+
+/// Where the value of a placeholder came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Argument,
+    ServiceVariable,
+    GlobalVariable,
+    DotEnv,
+    Environment,
+    Generator,
+    History,
+    Function,
+    Default,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let source = match self {
+            Source::Argument => "argument",
+            Source::ServiceVariable => "service variable",
+            Source::GlobalVariable => "global variable",
+            Source::DotEnv => ".env file",
+            Source::Environment => "environment",
+            Source::Generator => "generator",
+            Source::History => "previous run",
+            Source::Function => "built-in",
+            Source::Default => "default",
+        };
+        write!(f, "{}", source)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolved {
+    pub name: String,
+    /// A single value, or every value of a variadic placeholder.
+    pub values: Vec<String>,
+    pub source: Source,
+}
+
+impl Resolved {
+    pub fn value(&self) -> String {
+        self.values.join(" ")
+    }
+}
+
+/// Resolves every placeholder in `command`.
+///
+/// Named arguments win, then positional arguments in order of appearance,
+/// then `lookup` (variable store, .env file, environment, generator, history), then the inline
+/// default. `{env.NAME}` placeholders never consume positional arguments, and
+/// a trailing `{name...}` placeholder takes every remaining one. Built-in
+/// `{@...}` placeholders are evaluated for a command running in `cwd`.
+pub fn resolve_placeholders<F>(
+    command: &str,
+    args: Vec<String>,
+    named: &[(String, String)],
+    cwd: Option<&Path>,
+    lookup: F,
+) -> Result<Vec<Resolved>, Box<dyn Error>>
+where
+    F: Fn(&Placeholder) -> Result<Option<(String, Source)>, Box<dyn Error>>,
+{
+    let mut args = args.into_iter();
+    let mut resolved = Vec::new();
+    let mut missing = Vec::new();
+    let placeholders = placeholders(command);
+
+    if let Some(variadic) = misplaced_variadic(&placeholders) {
+        return Err(format!(
+            "{{{}...}} must be the last placeholder of the command",
+            variadic.name
+        )
+        .into());
+    }
+
+    for placeholder in placeholders {
+        if placeholder.is_function() {
+            let value = functions::evaluate(&placeholder.name, cwd)?;
+            resolved.push(Resolved {
+                name: placeholder.name,
+                values: vec![value],
+                source: Source::Function,
+            });
+            continue;
+        }
+
+        if let Some(min) = placeholder.variadic {
+            let mut values: Vec<String> = named
+                .iter()
+                .filter(|(name, _)| *name == placeholder.name)
+                .map(|(_, value)| value.clone())
+                .collect();
+            values.extend(args.by_ref());
+
+            let (values, source) = if !values.is_empty() {
+                (values, Source::Argument)
+            } else if let Some((value, source)) = lookup(&placeholder)? {
+                (vec![value], source)
+            } else if let Some(value) = placeholder.default {
+                (vec![value], Source::Default)
+            } else {
+                (values, Source::Argument)
+            };
+
+            if values.len() < min {
+                return Err(format!(
+                    "{{{}...}} needs at least {} values, got {}",
+                    placeholder.name,
+                    min,
+                    values.len()
+                )
+                .into());
+            }
+            resolved.push(Resolved {
+                name: placeholder.name,
+                values,
+                source,
+            });
+            continue;
+        }
+
+        let named_value = named
+            .iter()
+            .rev()
+            .find(|(name, _)| *name == placeholder.name)
+            .map(|(_, value)| (value.clone(), Source::Argument));
+
+        let mut value = named_value.or_else(|| {
+            if !placeholder.is_positional() {
+                None
+            } else {
+                args.next().map(|value| (value, Source::Argument))
+            }
+        });
+        if value.is_none() {
+            value = lookup(&placeholder)?;
+        }
+        let value = value.or_else(|| placeholder.default.map(|value| (value, Source::Default)));
+
+        match value {
+            Some((value, source)) => resolved.push(Resolved {
+                name: placeholder.name,
+                values: vec![value],
+                source,
+            }),
+            None => missing.push(placeholder.name),
+        }
+    }
+
+    if !missing.is_empty() {
+        return Err(format!(
+            "Not enough arguments to replace all placeholders: missing {}.\nuse smriti show -a youralias",
+            missing.join(", ")
+        )
+        .into());
+    }
+
+    Ok(resolved)
+}
+
+/// Substitutes resolved values into `command`, quoting them for `dialect`
+/// unless marked raw. Each value of a variadic placeholder is quoted on its own.
+pub fn render_command(
+    command: &str,
+    resolved: &[Resolved],
+    dialect: Dialect,
+) -> Result<String, Box<dyn Error>> {
+    substitute(command, resolved, |placeholder, value| {
+        if placeholder.raw {
+            return Ok(value.to_string());
+        }
+        quote_for(value, placeholder.quote, dialect).ok_or_else(|| {
+            format!(
+                "The value of {{{name}}} would change the code it is substituted into: {:?}\n\
+                 Mark it {{{name}:raw}} to insert it as it is, or pass it with --env and read it from the environment",
+                value,
+                name = placeholder.name
+            )
+            .into()
+        })
+    })
+}
+
+/// Substitutes resolved values into text that is not parsed by a shell, such
+/// as the value of an environment variable, without quoting them.
+pub fn render_value(text: &str, resolved: &[Resolved]) -> String {
+    let Ok(text) = substitute(text, resolved, |_, value| {
+        Ok::<_, Infallible>(value.to_string())
+    });
+    text
+}
+
+fn substitute<F, E>(command: &str, resolved: &[Resolved], mut word: F) -> Result<String, E>
+where
+    F: FnMut(&Placeholder, &str) -> Result<String, E>,
+{
+    let values: HashMap<&str, &[String]> = resolved
+        .iter()
+        .map(|r| (r.name.as_str(), r.values.as_slice()))
+        .collect();
+
+    let mut modified_command = String::new();
+    for segment in parse_command(command) {
+        match segment {
+            Segment::Literal(text) => modified_command.push_str(&text),
+            Segment::Placeholder(placeholder) => {
+                let value = values
+                    .get(placeholder.name.as_str())
+                    .copied()
+                    .unwrap_or_default();
+                let words = value
+                    .iter()
+                    .map(|value| word(&placeholder, value))
+                    .collect::<Result<Vec<String>, E>>()?;
+                modified_command.push_str(&words.join(" "));
+            }
+        }
+    }
+
+    Ok(modified_command)
+}
+
+#[cfg(test)]
+pub fn replace_placeholder(command: &str, args: Vec<String>) -> Result<String, Box<dyn Error>> {
+    let resolved = resolve_placeholders(command, args, &[], None, |_| Ok(None))?;
+    render_command(command, &resolved, Dialect::Posix)
+}
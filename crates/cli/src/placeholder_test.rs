@@ -0,0 +1,248 @@
+#[cfg(test)]
+mod tests {
+    use crate::placeholder::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_replace_placeholder() {
+        let command = replace_placeholder("nmap -sC -sV {ip} -p {port}", args(&["10.0.0.1", "22"]));
+        assert_eq!(command.unwrap(), "nmap -sC -sV 10.0.0.1 -p 22");
+
+        let result = replace_placeholder("scp {file} {host}:{file}", args(&["a.txt"]));
+        assert!(result.is_err(), "Missing arguments should be an error");
+    }
+
+    #[test]
+    fn test_shell_braces_are_not_placeholders() {
+        assert!(placeholders("echo ${HOME} ${USER:-root}").is_empty());
+        assert!(placeholders("find . -name '*.rs' -exec wc -l {} \\;").is_empty());
+        assert!(placeholders("echo {a,b}.txt").is_empty());
+
+        let command = replace_placeholder("cp ${HOME}/{file} /tmp", args(&["notes"]));
+        assert_eq!(command.unwrap(), "cp ${HOME}/notes /tmp");
+    }
+
+    #[test]
+    fn test_escaped_braces() {
+        let command = replace_placeholder("awk '{{print}}' {file}", args(&["log.txt"]));
+        assert_eq!(command.unwrap(), "awk '{print}' log.txt");
+
+        let command = replace_placeholder("docker ps --format '{{.Names}}'", vec![]);
+        assert_eq!(command.unwrap(), "docker ps --format '{{.Names}}'");
+    }
+
+    #[test]
+    fn test_ambiguous_braces() {
+        assert!(ambiguous_braces("nmap -sV {ip}").is_empty());
+        assert_eq!(ambiguous_braces("awk '{print}' {file}").len(), 1);
+        assert_eq!(ambiguous_braces("echo {{name}}").len(), 1);
+    }
+
+    #[test]
+    fn test_values_are_shell_quoted() {
+        let command = replace_placeholder("echo {msg}", args(&["foo; rm -rf ~"]));
+        assert_eq!(command.unwrap(), "echo 'foo; rm -rf ~'");
+
+        let command = replace_placeholder("echo {msg}", args(&["it's here"]));
+        assert_eq!(command.unwrap(), r"echo 'it'\''s here'");
+
+        let command = replace_placeholder("echo {msg}", args(&["line1\nline2"]));
+        assert_eq!(command.unwrap(), "echo 'line1\nline2'");
+
+        let command = replace_placeholder("ls {pattern}", args(&["*.rs"]));
+        assert_eq!(command.unwrap(), "ls '*.rs'");
+
+        let command = replace_placeholder("echo {msg}", args(&[""]));
+        assert_eq!(command.unwrap(), "echo ''");
+    }
+
+    #[test]
+    fn test_values_quoted_for_context() {
+        let command = replace_placeholder("echo \"hi {name}\"", args(&["$USER `id` \"x\""]));
+        assert_eq!(command.unwrap(), r#"echo "hi \$USER \`id\` \"x\"""#);
+
+        let command = replace_placeholder("echo 'hi {name}'", args(&["o'neil"]));
+        assert_eq!(command.unwrap(), r"echo 'hi o'\''neil'");
+
+        // Command substitutions have their own quotes, even inside double quotes
+        let value = "x'; touch /tmp/pwned; '";
+        let command = replace_placeholder("echo \"$(cat '{file}')\"", args(&[value]));
+        assert_eq!(
+            command.unwrap(),
+            r#"echo "$(cat 'x'\''; touch /tmp/pwned; '\''')""#
+        );
+        let command = replace_placeholder("echo \"`cat '{file}'`\"", args(&[value]));
+        assert_eq!(
+            command.unwrap(),
+            r#"echo "`cat 'x'\''; touch /tmp/pwned; '\'''`""#
+        );
+        let command =
+            replace_placeholder("echo \"$(echo $((1 + 2)) {a})\" {b}", args(&["a b", "c d"]));
+        assert_eq!(command.unwrap(), r#"echo "$(echo $((1 + 2)) 'a b')" 'c d'"#);
+    }
+
+    #[test]
+    fn test_values_quoted_for_interpreter() {
+        assert_eq!(Dialect::of(None), Dialect::Posix);
+        assert_eq!(Dialect::of(Some("/bin/bash -e")), Dialect::Posix);
+        assert_eq!(Dialect::of(Some("/usr/bin/env fish")), Dialect::Fish);
+        assert_eq!(Dialect::of(Some("python3 -u")), Dialect::Code);
+
+        let render = |command: &str, value: &str, dialect| {
+            let resolved =
+                resolve_placeholders(command, args(&[value]), &[], None, |_| Ok(None)).unwrap();
+            render_command(command, &resolved, dialect)
+        };
+
+        // fish reads `\'` as an escaped quote, so a trailing backslash is escaped too
+        assert_eq!(
+            render("echo {x} '{x}' \"{x}\"", r"a\", Dialect::Fish).unwrap(),
+            r#"echo 'a\\' 'a\\' "a\\""#
+        );
+        assert_eq!(
+            render("echo {x} \"{x}\"", "it's $HOME `id`", Dialect::Fish).unwrap(),
+            r#"echo 'it\'s $HOME `id`' "it's \$HOME `id`""#
+        );
+
+        // Code is left alone: values that would change it are refused
+        let print = "print('hello {name}')";
+        assert_eq!(
+            render(print, "world", Dialect::Code).unwrap(),
+            "print('hello world')"
+        );
+        assert!(render(print, "o'neil", Dialect::Code).is_err());
+        assert!(render(print, r"a\", Dialect::Code).is_err());
+        assert_eq!(
+            render("print({n} * 2)", "21", Dialect::Code).unwrap(),
+            "print(21 * 2)"
+        );
+        assert!(render("print({n})", "a b", Dialect::Code).is_err());
+        assert_eq!(
+            render("print({expr:raw})", "'o' + 'k'", Dialect::Code).unwrap(),
+            "print('o' + 'k')"
+        );
+    }
+
+    #[test]
+    fn test_raw_placeholder() {
+        let command = replace_placeholder("ls {opts:raw} {dir}", args(&["-la *.rs", "my dir"]));
+        assert_eq!(command.unwrap(), "ls -la *.rs 'my dir'");
+
+        let command = replace_placeholder("cp {file} {file:raw}.bak", args(&["a b"]));
+        assert_eq!(command.unwrap(), "cp 'a b' a b.bak");
+    }
+
+    #[test]
+    fn test_resolution_precedence() {
+        let command = "docker push {registry}/{image}:{tag=latest}";
+        let lookup = |placeholder: &Placeholder| match placeholder.name.as_str() {
+            "registry" => Ok(Some(("ghcr.io".to_string(), Source::GlobalVariable))),
+            _ => Ok(None),
+        };
+
+        let resolved = resolve_placeholders(command, args(&["ghcr.io", "app"]), &[], None, lookup);
+        assert!(resolved.is_ok());
+
+        let resolved = resolve_placeholders(command, vec![], &[], None, lookup);
+        assert!(resolved.is_err(), "{{image}} has no value");
+
+        let named = vec![("image".to_string(), "app".to_string())];
+        let resolved = resolve_placeholders(command, vec![], &named, None, lookup).unwrap();
+        let sources: Vec<Source> = resolved.iter().map(|r| r.source).collect();
+        assert_eq!(
+            sources,
+            vec![Source::GlobalVariable, Source::Argument, Source::Default]
+        );
+        assert_eq!(
+            render_command(command, &resolved, Dialect::Posix).unwrap(),
+            "docker push ghcr.io/app:latest"
+        );
+    }
+
+    #[test]
+    fn test_env_placeholders_skip_positional_arguments() {
+        let command = "aws --profile {env.AWS_PROFILE=default} s3 ls {bucket}";
+        let lookup = |placeholder: &Placeholder| match placeholder.name.as_str() {
+            "env.AWS_PROFILE" => Ok(Some(("prod".to_string(), Source::Environment))),
+            _ => Ok(None),
+        };
+
+        let resolved = resolve_placeholders(command, args(&["logs"]), &[], None, lookup).unwrap();
+        assert_eq!(
+            render_command(command, &resolved, Dialect::Posix).unwrap(),
+            "aws --profile prod s3 ls logs"
+        );
+
+        let resolved =
+            resolve_placeholders(command, args(&["logs"]), &[], None, |_| Ok(None)).unwrap();
+        assert_eq!(resolved[0].source, Source::Default);
+    }
+
+    #[test]
+    fn test_variadic_placeholder() {
+        let command =
+            replace_placeholder("git add {files...}", args(&["a.rs", "my file.rs", "*.md"]));
+        assert_eq!(command.unwrap(), "git add a.rs 'my file.rs' '*.md'");
+
+        let command = replace_placeholder("kubectl delete pod -n {ns} {pods...1}", args(&["dev"]));
+        assert!(command.is_err(), "{{pods...1}} needs at least one value");
+
+        let command = replace_placeholder("ls {dirs...}", vec![]);
+        assert_eq!(command.unwrap(), "ls ");
+
+        let command = replace_placeholder("ls {dirs...=.}", vec![]);
+        assert_eq!(command.unwrap(), "ls .");
+
+        let command = replace_placeholder("echo {words...} {last}", args(&["a", "b"]));
+        assert!(command.is_err(), "Variadic placeholder must be last");
+        assert_eq!(ambiguous_braces("echo {words...} {last}").len(), 1);
+    }
+
+    #[test]
+    fn test_builtin_placeholders() {
+        let resolved = resolve_placeholders(
+            "tar czf backup-{@date:%Y}-{@random:8}.tgz {dir} # {@uuid}",
+            args(&["src"]),
+            &[],
+            None,
+            |_| Ok(None),
+        )
+        .unwrap();
+        let values: Vec<String> = resolved.iter().map(|r| r.value()).collect();
+
+        assert_eq!(values[0].len(), 4);
+        assert_eq!(values[1].len(), 8);
+        assert_eq!(values[2], "src");
+        assert_eq!(values[3].len(), 36);
+        assert_eq!(resolved[0].source, Source::Function);
+
+        let command = replace_placeholder("cp {files...} /backup/{@cwd}", args(&["a", "b"]));
+        assert!(
+            command.is_ok(),
+            "Built-ins do not take positional arguments"
+        );
+
+        assert!(replace_placeholder("echo {@nope}", vec![]).is_err());
+        assert!(replace_placeholder("echo {@random:x}", vec![]).is_err());
+        assert!(replace_placeholder("echo {@date:%Q}", vec![]).is_err());
+    }
+
+    #[test]
+    fn test_builtin_placeholders_use_working_directory() {
+        let dir = std::env::temp_dir();
+        let resolved =
+            resolve_placeholders("ls {@cwd}", vec![], &[], Some(&dir), |_| Ok(None)).unwrap();
+        assert_eq!(resolved[0].value(), dir.display().to_string());
+
+        // The branch is looked up where the command runs, not where smriti does
+        let root = std::path::Path::new("/");
+        let branch =
+            resolve_placeholders("git push {@git_branch}", vec![], &[], Some(root), |_| {
+                Ok(None)
+            });
+        assert!(branch.is_err());
+    }
+}
@@ -0,0 +1,81 @@
+use colored::Colorize;
+use std::error::Error;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turns prompts off, or back on, for commands resolved to run without a user
+/// watching, such as scheduled ones.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// Whether there is a user at the terminal to answer prompts.
+pub fn can_prompt() -> bool {
+    ENABLED.load(Ordering::SeqCst) && io::stdin().is_terminal()
+}
+
+/// Asks the user to pick one of `choices` for a placeholder, or type a value.
+pub fn choose(name: &str, choices: &[String]) -> Result<String, Box<dyn Error>> {
+    if choices.is_empty() {
+        return Err(format!("No values available to choose from for {{{}}}", name).into());
+    }
+    if !can_prompt() {
+        return Err(format!(
+            "No value for {{{}}} and no terminal to choose one from",
+            name
+        )
+        .into());
+    }
+
+    eprintln!("{}", format!("Choose a value for {{{}}}:", name).cyan());
+    for (i, choice) in choices.iter().enumerate() {
+        eprintln!("  {}) {}", i + 1, choice);
+    }
+    eprint!("> [1]: ");
+    io::stderr().flush()?;
+
+    let mut input = String::new();
+    io::stdin().lock().read_line(&mut input)?;
+    let input = input.trim();
+
+    // Enter picks the first entry, a number picks that entry, anything else is taken as typed
+    if input.is_empty() {
+        return Ok(choices[0].clone());
+    }
+    match input.parse::<usize>() {
+        Ok(n) if (1..=choices.len()).contains(&n) => Ok(choices[n - 1].clone()),
+        _ => Ok(input.to_string()),
+    }
+}
+
+/// Asks a free-form question, returning `default` when the user just presses Enter.
+pub fn ask(question: &str, default: &str) -> Result<String, Box<dyn Error>> {
+    eprint!("{} [{}]: ", question.cyan(), default);
+    io::stderr().flush()?;
+
+    let mut input = String::new();
+    io::stdin().lock().read_line(&mut input)?;
+    let input = input.trim();
+
+    if input.is_empty() {
+        Ok(default.to_string())
+    } else {
+        Ok(input.to_string())
+    }
+}
+
+/// Asks the user to type `expected` to go ahead, e.g. before a dangerous command.
+pub fn confirm(expected: &str) -> Result<bool, Box<dyn Error>> {
+    if !can_prompt() {
+        return Err("No terminal to confirm on, pass --yes to run anyway".into());
+    }
+
+    eprint!("{} ", format!("Type {} to run it:", expected).yellow());
+    io::stderr().flush()?;
+
+    let mut input = String::new();
+    io::stdin().lock().read_line(&mut input)?;
+    Ok(input.trim() == expected)
+}
@@ -1,76 +1,706 @@
+use crate::exit_code;
+use cli::format_duration;
 use colored::Colorize;
 use regex::Regex;
+use std::env;
 use std::error::Error;
-use std::{collections::HashMap, process};
-use subprocess::{Exec, Redirection};
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use subprocess::{Exec, ExitStatus, Popen, PopenConfig, Redirection};
 
-pub fn replace_placeholder(command: &str, args: Vec<String>) -> Result<String, Box<dyn Error>> {
-    let pattern = r"\{([a-zA-Z_][a-zA-Z0-9_]*)\}"; // match placeholders starts with {}
-    let re = Regex::new(pattern).unwrap();
+/// Runs a placeholder generator and returns its non-empty output lines,
+/// reduced to a single whitespace-separated column (1-based) when given.
+pub fn generate_choices(
+    generator: &str,
+    column: Option<usize>,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let result = Exec::shell(generator)
+        .stdout(Redirection::Pipe)
+        .stderr(Redirection::Pipe)
+        .capture()?;
 
-    let mut placeholder_map: HashMap<String, String> = HashMap::new();
+    if !result.success() {
+        return Err(format!(
+            "Generator `{}` failed: {}",
+            generator,
+            String::from_utf8_lossy(&result.stderr).trim()
+        )
+        .into());
+    }
+
+    let stdout = String::from_utf8_lossy(&result.stdout);
+    let choices = stdout
+        .lines()
+        .filter_map(|line| match column {
+            Some(n) => line.split_whitespace().nth(n.saturating_sub(1)),
+            None => Some(line.trim()),
+        })
+        .filter(|choice| !choice.is_empty())
+        .map(|choice| choice.to_string())
+        .collect();
+
+    Ok(choices)
+}
+
+// How an interactive program can be given work to do, after which it may
+// run without the terminal, e.g. `python3 manage.py migrate`.
+struct Work {
+    /// Flags that give the program a command or script, e.g. `-c`.
+    flags: &'static [&'static str],
+    /// How many arguments, besides options, make it work on its own, e.g.
+    /// 2 for `ssh host uptime`. 0 when arguments never do.
+    arguments: usize,
+    /// Options that take the next word as their value, e.g. `-p 22`.
+    values: &'static [&'static str],
+}
+
+const SESSION: Work = Work {
+    flags: &[],
+    arguments: 0,
+    values: &[],
+};
+
+// Programs that take over the terminal and break when their output is piped.
+const INTERACTIVE_PROGRAMS: &[(&str, Work)] = &[
+    (
+        "ssh",
+        Work {
+            flags: &[],
+            arguments: 2,
+            values: &[
+                "-B", "-b", "-c", "-D", "-E", "-e", "-F", "-I", "-i", "-J", "-L", "-l", "-m", "-O",
+                "-o", "-p", "-Q", "-R", "-S", "-W", "-w",
+            ],
+        },
+    ),
+    (
+        "mosh",
+        Work {
+            flags: &[],
+            arguments: 2,
+            values: &["-p", "--port", "--ssh", "--server", "--predict"],
+        },
+    ),
+    ("telnet", SESSION),
+    ("vim", SESSION),
+    ("vi", SESSION),
+    ("nvim", SESSION),
+    ("nano", SESSION),
+    ("emacs", SESSION),
+    ("less", SESSION),
+    ("more", SESSION),
+    ("man", SESSION),
+    ("top", SESSION),
+    ("htop", SESSION),
+    ("btop", SESSION),
+    ("watch", SESSION),
+    ("tmux", SESSION),
+    ("screen", SESSION),
+    (
+        "psql",
+        Work {
+            flags: &["-c", "--command", "-f", "--file", "-l", "--list"],
+            arguments: 0,
+            values: &[],
+        },
+    ),
+    (
+        "mysql",
+        Work {
+            flags: &["-e", "--execute"],
+            arguments: 0,
+            values: &[],
+        },
+    ),
+    (
+        "mongosh",
+        Work {
+            flags: &["--eval", "-f", "--file"],
+            arguments: 0,
+            values: &[],
+        },
+    ),
+    (
+        "redis-cli",
+        Work {
+            flags: &[],
+            arguments: 1,
+            values: &["-h", "-p", "-a", "-n", "-u", "-s", "--user", "--pass"],
+        },
+    ),
+    (
+        "sqlite3",
+        Work {
+            flags: &["-cmd"],
+            arguments: 2,
+            values: &["-separator", "-newline", "-nullvalue"],
+        },
+    ),
+    ("python", PYTHON),
+    ("python3", PYTHON),
+    ("ipython", PYTHON),
+    (
+        "node",
+        Work {
+            flags: &["-e", "--eval", "-p", "--print"],
+            arguments: 1,
+            values: &["-r", "--require"],
+        },
+    ),
+    (
+        "irb",
+        Work {
+            flags: &[],
+            arguments: 1,
+            values: &["-r", "-I"],
+        },
+    ),
+    (
+        "ghci",
+        Work {
+            flags: &["-e"],
+            arguments: 0,
+            values: &[],
+        },
+    ),
+    ("fzf", SESSION),
+];
+
+const PYTHON: Work = Work {
+    flags: &["-c", "-m"],
+    arguments: 1,
+    values: &["-W", "-X"],
+};
+
+/// Whether a command looks like it needs the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interactive {
+    No,
+    /// An interactive program given work to do, such as `python3 manage.py
+    /// migrate` or `psql -c 'select 1'`, which may still need the terminal.
+    Maybe,
+    /// An interactive program on its own, such as `ssh host` or `psql -h db`.
+    Yes,
+}
 
-    // collect placeholders from the command
-    let mut placeholders: Vec<String> = Vec::new();
-    for cap in re.captures_iter(command) {
-        let placeholder = cap[0].to_string();
-        if !placeholders.contains(&placeholder) {
-            placeholders.push(placeholder); // add unique placeholders
+/// Whether a command starts a program that needs the terminal.
+pub fn looks_interactive(command: &str) -> Interactive {
+    let mut words = command.split_whitespace();
+    let program = match words.next() {
+        Some("sudo") | Some("exec") => words.find(|word| !word.starts_with('-')),
+        word => word,
+    };
+    let Some(program) = program.map(|program| program.rsplit('/').next().unwrap_or(program)) else {
+        return Interactive::No;
+    };
+    let Some((_, work)) = INTERACTIVE_PROGRAMS
+        .iter()
+        .find(|(name, _)| *name == program)
+    else {
+        return Interactive::No;
+    };
+
+    // Input or output that is not the terminal means there is no session to take over
+    if command.contains(['|', '<', '>']) {
+        return Interactive::Maybe;
+    }
+    let mut arguments = 0;
+    while let Some(word) = words.next() {
+        let given = |flag: &&str| {
+            word == *flag
+                || word.starts_with(&format!("{}=", flag))
+                || (flag.len() == 2 && !word.starts_with("--") && word.starts_with(flag))
+        };
+        if work.flags.iter().any(given) {
+            return Interactive::Maybe;
+        }
+        if work.values.contains(&word) {
+            words.next();
+        } else if !word.starts_with('-') {
+            arguments += 1;
         }
     }
+    match work.arguments {
+        0 => Interactive::Yes,
+        needed if arguments >= needed => Interactive::Maybe,
+        _ => Interactive::Yes,
+    }
+}
 
-    // if there are no placeholders, return the original command
-    if placeholders.is_empty() {
-        return Ok(command.to_string());
+/// Finds a program on the PATH, or checks the path it was given.
+pub fn find_program(program: &str) -> Option<PathBuf> {
+    if program.contains('/') {
+        let path = Path::new(program);
+        return path.is_file().then(|| path.to_path_buf());
     }
+    env::var_os("PATH").and_then(|paths| {
+        env::split_paths(&paths)
+            .map(|dir| dir.join(program))
+            .find(|path| path.is_file())
+    })
+}
 
-    if placeholders.len() > args.len() {
-        return Err(
-            "Not enough arguments to replace all placeholders.\nuse smriti search -a youralias"
-                .into(),
-        );
+/// Checks that the program of an interpreter such as `bash` or `python3 -u` exists.
+pub fn check_interpreter(interpreter: &str) -> Result<(), Box<dyn Error>> {
+    match interpreter.split_whitespace().next() {
+        Some(program) if find_program(program).is_some() => Ok(()),
+        Some(program) => Err(format!("Interpreter `{}` was not found", program).into()),
+        None => Err("Interpreter is empty".into()),
     }
+}
+
+// Runs the command with `sh -c`, or passes it to the interpreter as inline
+// code with the flag that interpreter expects.
+fn build_argv(command: &str, interpreter: Option<&str>) -> Vec<OsString> {
+    let mut words = interpreter.unwrap_or("sh").split_whitespace();
+    let program = words.next().unwrap_or("sh");
+    // Found up front, as the command may run with a cleared PATH
+    let path = find_program(program).unwrap_or_else(|| PathBuf::from(program));
+
+    let name = program.rsplit('/').next().unwrap_or(program);
+    let flag = match name {
+        "node" | "nodejs" | "ruby" | "perl" | "deno" => "-e",
+        _ => "-c",
+    };
+    let mut argv = vec![path.into_os_string()];
+    argv.extend(words.map(OsString::from));
+    argv.push(flag.into());
+    argv.push(command.into());
+    argv
+}
 
-    // mapping each placeholder to an argument
-    for (i, placeholder) in placeholders.iter().enumerate() {
-        placeholder_map.insert(placeholder.clone(), args[i].clone());
+/// The interpreter named by a script's `#!` line, e.g. `/usr/bin/env python3`.
+pub fn shebang(script: &str) -> Option<&str> {
+    script
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("#!"))
+        .map(str::trim)
+        .filter(|interpreter| !interpreter.is_empty())
+}
+
+// A script written to a temporary file for the length of a run.
+struct TempScript(PathBuf);
+
+impl TempScript {
+    fn write(script: &str) -> io::Result<TempScript> {
+        let path = env::temp_dir().join(format!("smriti-{}", uuid::Uuid::new_v4()));
+        // Scripts often hold credentials, so only the user may read them
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)?
+            .write_all(script.as_bytes())?;
+        Ok(TempScript(path))
     }
+}
 
-    // replace placeholders in the command with their corresponding arguments
-    let mut modified_command = command.to_string();
-    for (placeholder, replacement) in placeholder_map {
-        modified_command = modified_command.replace(&placeholder, &replacement);
+impl Drop for TempScript {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
     }
+}
+
+// Runs a script file with the interpreter of its `#!` line, or else the given
+// one. The file is passed as an argument, so it needs no execute permission.
+fn build_script_argv(script: &str, path: &Path, interpreter: Option<&str>) -> Vec<OsString> {
+    let mut words = shebang(script)
+        .or(interpreter)
+        .unwrap_or("sh")
+        .split_whitespace();
+    let program = words.next().unwrap_or("sh");
+    let program = find_program(program).unwrap_or_else(|| PathBuf::from(program));
 
-    Ok(modified_command)
+    let mut argv = vec![program.into_os_string()];
+    argv.extend(words.map(OsString::from));
+    argv.push(path.as_os_str().to_owned());
+    argv
 }
 
-#[allow(dead_code)]
-pub fn execute_command(command: String) -> Result<(), Box<dyn Error>> {
-    println!("{}", command.green());
-    let result = Exec::shell(command)
-        .stdout(Redirection::Pipe) // Pipe stdout to capture output
-        .stderr(Redirection::Pipe) // Pipe stderr to capture errors
-        .capture()?; // Capture the output and wait for the command to complete
+// The first line of a script, with a note of how many follow.
+fn first_line(command: &str) -> String {
+    let mut lines = command.lines();
+    let first = lines.next().unwrap_or_default();
+    match lines.count() {
+        0 => first.to_string(),
+        1 => format!("{} (+1 more line)", first),
+        more => format!("{} (+{} more lines)", first, more),
+    }
+}
+
+/// Expands a leading `~` and `$NAME` or `${NAME}` environment variables in a
+/// directory, which must exist.
+pub fn expand_directory(path: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let variable =
+        Regex::new(r"\$(?:\{([a-zA-Z_][a-zA-Z0-9_]*)\}|([a-zA-Z_][a-zA-Z0-9_]*))").unwrap();
+    let mut missing = None;
+    let expanded = variable.replace_all(path, |captures: &regex::Captures| {
+        let name = captures.get(1).or(captures.get(2)).unwrap().as_str();
+        env::var(name).unwrap_or_else(|_| {
+            missing = Some(name.to_string());
+            String::new()
+        })
+    });
+    if let Some(name) = missing {
+        return Err(format!("Environment variable {} in {} is not set", name, path).into());
+    }
+
+    let directory = match expanded.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            let home = dirs::home_dir().ok_or("Home directory not found")?;
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(expanded.as_ref()),
+    };
 
-    // Print stdout and stderr
-    if !result.stdout.is_empty() {
-        println!("Output:\n{}", String::from_utf8_lossy(&result.stdout));
+    if !directory.is_dir() {
+        return Err(format!("Directory {} does not exist", directory.display()).into());
     }
-    if !result.stderr.is_empty() {
-        eprintln!("Error:\n{}", String::from_utf8_lossy(&result.stderr).red());
+    Ok(directory)
+}
+
+/// How a command is executed.
+#[derive(Debug, Default)]
+pub struct ExecOptions {
+    /// Also collect the output so it can be stored.
+    pub capture: bool,
+    /// Interpreter used instead of `/bin/sh`, e.g. `bash` or `python3`.
+    pub interpreter: Option<String>,
+    /// Directory to run in instead of the current one.
+    pub cwd: Option<PathBuf>,
+    /// Environment variables set on top of smriti's own environment.
+    pub env: Vec<(String, String)>,
+    /// Start from an empty environment instead of smriti's.
+    pub clear_env: bool,
+    /// Stop the command and everything it started after this long.
+    pub timeout: Option<Duration>,
+    /// Put this in front of every line of output, to tell commands apart.
+    pub prefix: Option<String>,
+    /// The command is a multi-line script, run from a temporary file.
+    pub script: bool,
+    /// Stop the command and everything it started once this is set.
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+impl ExecOptions {
+    /// Whether the run was asked to stop through `cancel`.
+    pub fn cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.load(Ordering::SeqCst))
     }
+}
 
-    // Check if the command was successful
-    if result.success() {
-        // println!("Command executed successfully");
-        process::exit(0);
+/// How a command run ended.
+pub struct RunResult {
+    pub status: ExitStatus,
+    pub duration: Duration,
+    /// Interleaved stdout and stderr, when the run was captured.
+    pub output: Option<String>,
+    /// The command was stopped because it ran past its timeout.
+    pub timed_out: bool,
+}
+
+impl RunResult {
+    /// Shell-style exit code: the child's own code, or 128 + signal, or
+    /// 124 when it timed out like with `timeout(1)`.
+    pub fn exit_code(&self) -> Option<i32> {
+        if self.timed_out {
+            return Some(exit_code::TIMEOUT);
+        }
+        match self.status {
+            ExitStatus::Exited(code) => Some(code as i32),
+            ExitStatus::Signaled(signal) => Some(128 + signal as i32),
+            _ => None,
+        }
+    }
+}
+
+// Copies a child's output to our own as it arrives, keeping a copy in `log`.
+// With a prefix it is copied in whole lines, each starting with the prefix,
+// so lines of commands running side by side do not mix.
+fn tee<R, W>(
+    reader: R,
+    mut writer: W,
+    prefix: Option<String>,
+    log: Arc<Mutex<Vec<u8>>>,
+) -> JoinHandle<()>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut chunk = Vec::new();
+        loop {
+            chunk.clear();
+            let read = match prefix {
+                Some(_) => reader.read_until(b'\n', &mut chunk),
+                None => reader.fill_buf().map(|data| {
+                    chunk.extend_from_slice(data);
+                    data.len()
+                }),
+            };
+            match read {
+                Ok(0) => break,
+                Ok(n) if prefix.is_none() => reader.consume(n),
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            }
+            log.lock().unwrap().extend_from_slice(&chunk);
+
+            if let Some(ref prefix) = prefix {
+                let mut line = prefix.as_bytes().to_vec();
+                line.extend_from_slice(&chunk);
+                if !line.ends_with(b"\n") {
+                    line.push(b'\n');
+                }
+                chunk = line;
+            }
+            let _ = writer.write_all(&chunk);
+            let _ = writer.flush();
+        }
+    })
+}
+
+// How long a timed out command gets to exit after SIGTERM before it is killed.
+const KILL_GRACE: Duration = Duration::from_secs(5);
+
+// Signals smriti passes on to commands running in their own process group,
+// which no longer get them from the terminal.
+const FORWARDED_SIGNALS: [libc::c_int; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+
+// Signals the terminal sends to a command sharing smriti's process group,
+// which smriti leaves to the command while it waits, as system(3) does.
+const TERMINAL_SIGNALS: [libc::c_int; 2] = [libc::SIGINT, libc::SIGQUIT];
+
+static SIGNALS_RECEIVED: AtomicUsize = AtomicUsize::new(0);
+static LAST_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+extern "C" fn remember_signal(signal: libc::c_int) {
+    LAST_SIGNAL.store(signal, Ordering::SeqCst);
+    SIGNALS_RECEIVED.fetch_add(1, Ordering::SeqCst);
+}
+
+// For each caught signal, the number of waits catching it and the handler it
+// replaced, put back when the last of them finishes.
+static CAUGHT: Mutex<Vec<(libc::c_int, usize, libc::sighandler_t)>> = Mutex::new(Vec::new());
+
+// Catches the given signals with `remember_signal` for as long as it lives.
+// Caught rather than ignored signals are reset for the commands started
+// meanwhile, where ignored ones would be inherited.
+struct CaughtSignals(&'static [libc::c_int]);
+
+impl CaughtSignals {
+    fn start(signals: &'static [libc::c_int]) -> CaughtSignals {
+        let mut caught = CAUGHT.lock().unwrap();
+        for &signal in signals {
+            match caught.iter_mut().find(|(caught, _, _)| *caught == signal) {
+                Some((_, count, _)) => *count += 1,
+                None => {
+                    let previous = unsafe {
+                        libc::signal(signal, remember_signal as *const () as libc::sighandler_t)
+                    };
+                    caught.push((signal, 1, previous));
+                }
+            }
+        }
+        CaughtSignals(signals)
+    }
+}
+
+impl Drop for CaughtSignals {
+    fn drop(&mut self) {
+        let mut caught = CAUGHT.lock().unwrap();
+        for &signal in self.0 {
+            if let Some(i) = caught.iter().position(|(caught, _, _)| *caught == signal) {
+                caught[i].1 -= 1;
+                if caught[i].1 == 0 {
+                    let (_, _, previous) = caught.remove(i);
+                    unsafe { libc::signal(signal, previous) };
+                }
+            }
+        }
+    }
+}
+
+// Waits for a command started in its own process group, sending SIGTERM to
+// the whole group when the timeout passes or the run is cancelled, and
+// SIGKILL when it does not exit. Returns the status and whether the command
+// timed out.
+fn wait_in_group(
+    process: &mut Popen,
+    options: &ExecOptions,
+    prefix: &str,
+) -> Result<(ExitStatus, bool), Box<dyn Error>> {
+    let group = process
+        .pid()
+        .ok_or("Command exited before it could be watched")? as libc::pid_t;
+    let kill_group = |signal| unsafe {
+        libc::killpg(group, signal);
+    };
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+
+    let _forwarding = CaughtSignals::start(&FORWARDED_SIGNALS);
+    let mut seen = SIGNALS_RECEIVED.load(Ordering::SeqCst);
+    loop {
+        let received = SIGNALS_RECEIVED.load(Ordering::SeqCst);
+        if received != seen {
+            seen = received;
+            kill_group(LAST_SIGNAL.load(Ordering::SeqCst));
+        }
+        if let Some(status) = process.wait_timeout(Duration::from_millis(50))? {
+            return Ok((status, false));
+        }
+        if options.cancelled() {
+            break;
+        }
+        if let (Some(deadline), Some(timeout)) = (deadline, options.timeout) {
+            if Instant::now() >= deadline {
+                eprintln!(
+                    "{}{}",
+                    prefix,
+                    format!("Timed out after {}, stopping it", format_duration(timeout)).red()
+                );
+                break;
+            }
+        }
+    }
+
+    let timed_out = !options.cancelled();
+    kill_group(libc::SIGTERM);
+    let status = match process.wait_timeout(KILL_GRACE)? {
+        Some(status) => status,
+        None => {
+            kill_group(libc::SIGKILL);
+            process.wait()?
+        }
+    };
+    // Children that outlived the command would keep its output open
+    kill_group(libc::SIGKILL);
+    Ok((status, timed_out))
+}
+
+/// Runs a command with its output streamed to the terminal as it is produced.
+pub fn execute_command(
+    command: String,
+    options: &ExecOptions,
+) -> Result<RunResult, Box<dyn Error>> {
+    let prefix = options.prefix.as_deref().unwrap_or_default();
+    println!("{}{}", prefix, first_line(&command).green());
+    let started = Instant::now();
+    let script = match options.script {
+        true => Some(TempScript::write(&command)?),
+        false => None,
+    };
+    let argv = match script {
+        Some(ref file) => build_script_argv(&command, &file.0, options.interpreter.as_deref()),
+        None => build_argv(&command, options.interpreter.as_deref()),
+    };
+
+    let env = options
+        .env
+        .iter()
+        .map(|(name, value)| (OsString::from(name), OsString::from(value)));
+    let env = if options.clear_env {
+        Some(env.collect())
+    } else if options.env.is_empty() {
+        None
     } else {
+        Some(PopenConfig::current_env().into_iter().chain(env).collect())
+    };
+
+    // A command that may be stopped gets its own process group so everything
+    // it starts can be stopped together. It is no longer in the terminal's
+    // foreground group then, and reading from the terminal would stop it.
+    let own_group = options.timeout.is_some() || options.cancel.is_some();
+    let stdin = if own_group && io::stdin().is_terminal() {
+        Redirection::File(File::open("/dev/null")?)
+    } else {
+        Redirection::None
+    };
+    // Inherited stdout and stderr keep colors and interleaving intact
+    let piped = options.capture || options.prefix.is_some();
+    let output = || match piped {
+        true => Redirection::Pipe,
+        false => Redirection::None,
+    };
+    let mut process = Popen::create(
+        &argv,
+        PopenConfig {
+            stdin,
+            stdout: output(),
+            stderr: output(),
+            env,
+            cwd: options.cwd.as_ref().map(|cwd| cwd.as_os_str().to_owned()),
+            setpgid: own_group,
+            ..Default::default()
+        },
+    )?;
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let readers = if piped {
+        vec![
+            tee(
+                process.stdout.take().unwrap(),
+                io::stdout(),
+                options.prefix.clone(),
+                log.clone(),
+            ),
+            tee(
+                process.stderr.take().unwrap(),
+                io::stderr(),
+                options.prefix.clone(),
+                log.clone(),
+            ),
+        ]
+    } else {
+        vec![]
+    };
+
+    let (status, timed_out) = match own_group {
+        true => wait_in_group(&mut process, options, prefix)?,
+        false => {
+            let _ignoring = CaughtSignals::start(&TERMINAL_SIGNALS);
+            (process.wait()?, false)
+        }
+    };
+    for reader in readers {
+        let _ = reader.join();
+    }
+
+    let output = options.capture.then(|| {
+        let mut output = String::from_utf8_lossy(&log.lock().unwrap()).into_owned();
+        if let (true, Some(timeout)) = (timed_out, options.timeout) {
+            output.push_str(&format!(
+                "[smriti] timed out after {}\n",
+                format_duration(timeout)
+            ));
+        }
+        output
+    });
+
+    if !status.success() && !timed_out && !options.cancelled() {
         eprintln!(
-            "{}",
-            format!("Command failed with status: {:?}", result.exit_status).red()
+            "{}{}",
+            prefix,
+            format!("Command failed with status: {:?}", status).red()
         );
     }
 
-    Ok(())
+    Ok(RunResult {
+        status,
+        duration: started.elapsed(),
+        output,
+        timed_out,
+    })
 }
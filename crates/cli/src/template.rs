@@ -0,0 +1,264 @@
+use crate::placeholder::{quote_for, Dialect, Quote, Resolved, Source};
+use minijinja::value::{Value, ValueKind};
+use minijinja::{Environment, ErrorKind};
+use regex::Regex;
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::error::Error;
+
+// Values are quoted for `dialect` when printed unless marked `|safe`.
+fn environment(dialect: Dialect) -> Environment<'static> {
+    let mut environment = Environment::new();
+    environment.add_global("env", Value::from(env::vars().collect::<BTreeMap<_, _>>()));
+    environment.add_filter(SINGLE_QUOTED, move |value: Value| {
+        quote_in(value, Quote::Single, dialect)
+    });
+    environment.add_filter(DOUBLE_QUOTED, move |value: Value| {
+        quote_in(value, Quote::Double, dialect)
+    });
+    environment.set_formatter(move |out, _state, value| {
+        let text = if value.is_safe() {
+            value.to_string()
+        } else {
+            quote_words(value, Quote::None, dialect)?
+        };
+        out.write_str(&text).map_err(minijinja::Error::from)
+    });
+    environment
+}
+
+// Filters added to `{{ }}` tags inside shell quotes, see `quote_outputs`.
+const SINGLE_QUOTED: &str = "smriti_single_quoted";
+const DOUBLE_QUOTED: &str = "smriti_double_quoted";
+
+// Quotes a value, or each item of a list, for the given context.
+fn quote_words(value: &Value, quote: Quote, dialect: Dialect) -> Result<String, minijinja::Error> {
+    let quote = |value: String| {
+        quote_for(&value, quote, dialect).ok_or_else(|| {
+            minijinja::Error::new(
+                ErrorKind::InvalidOperation,
+                format!(
+                    "{:?} would change the code it is substituted into, mark it |safe to insert it as it is",
+                    value
+                ),
+            )
+        })
+    };
+    if value.is_undefined() || value.is_none() {
+        Ok(String::new())
+    } else if value.kind() == ValueKind::Seq {
+        let words = value
+            .try_iter()?
+            .map(|item| quote(item.to_string()))
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(words.join(" "))
+    } else {
+        quote(value.to_string())
+    }
+}
+
+fn quote_in(value: Value, quote: Quote, dialect: Dialect) -> Result<Value, minijinja::Error> {
+    match value.is_safe() {
+        true => Ok(value),
+        false => Ok(Value::from_safe_string(quote_words(
+            &value, quote, dialect,
+        )?)),
+    }
+}
+
+// The tags of a template: comments and raw blocks (neither captured), `{{ }}`
+// output tags (group 1) and `{% %}` statements (group 2).
+fn tag_regex() -> Regex {
+    Regex::new(
+        r"(?s)\{#.*?#\}|\{%[-+]?\s*raw\s*-?%\}.*?\{%[-+]?\s*endraw\s*-?%\}|\{\{(.*?)\}\}|\{%(.*?)%\}",
+    )
+    .unwrap()
+}
+
+// The shell quoting context after `text`, starting in `quote`.
+fn quote_after(text: &str, mut quote: Quote) -> Quote {
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        quote = match (c, quote) {
+            ('\\', Quote::None | Quote::Double) => {
+                chars.next();
+                quote
+            }
+            ('\'', Quote::None) => Quote::Single,
+            ('\'', Quote::Single) => Quote::None,
+            ('"', Quote::None) => Quote::Double,
+            ('"', Quote::Double) => Quote::None,
+            _ => quote,
+        };
+    }
+    quote
+}
+
+// Rewrites `{{ value }}` tags inside shell quotes to `{{ (value)|filter }}`
+// so their values are escaped for the quotes rather than quoted again.
+fn quote_outputs(source: &str) -> String {
+    let mut rewritten = String::new();
+    let mut quote = Quote::None;
+    let mut last = 0;
+
+    for tag in tag_regex().captures_iter(source) {
+        let whole = tag.get(0).unwrap();
+        quote = quote_after(&source[last..whole.start()], quote);
+        rewritten.push_str(&source[last..whole.start()]);
+        last = whole.end();
+
+        let filter = match quote {
+            Quote::Single => SINGLE_QUOTED,
+            Quote::Double => DOUBLE_QUOTED,
+            Quote::None => "",
+        };
+        match tag.get(1) {
+            Some(expression) if !filter.is_empty() => {
+                // Keep whitespace control markers such as `{{- value -}}` in place
+                let inner = expression.as_str();
+                let body = inner.trim_start_matches(['-', '+']);
+                let open = &inner[..inner.len() - body.len()];
+                let body = body.trim_end_matches(['-', '+']);
+                let close = &inner[open.len() + body.len()..];
+                rewritten.push_str(&format!("{{{{{}({})|{}{}}}}}", open, body, filter, close));
+            }
+            _ => rewritten.push_str(whole.as_str()),
+        }
+    }
+    rewritten.push_str(&source[last..]);
+    rewritten
+}
+
+// Names inside the tags of a template in order, and whether each was inside
+// a `{{ }}` tag. Text outside tags, comments, string literals, attributes
+// and filter names are skipped.
+fn tag_names(source: &str) -> Vec<(String, bool)> {
+    let word =
+        Regex::new(r#""(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*'|([.|]\s*)?\b([a-zA-Z_][a-zA-Z0-9_]*)"#)
+            .unwrap();
+    let mut names = Vec::new();
+
+    for tag in tag_regex().captures_iter(source) {
+        let (expression, output) = match (tag.get(1), tag.get(2)) {
+            (Some(expression), _) => (expression, true),
+            (None, Some(statement)) => (statement, false),
+            (None, None) => continue,
+        };
+        for word in word.captures_iter(expression.as_str()) {
+            if let (None, Some(name)) = (word.get(1), word.get(2)) {
+                names.push((name.as_str().to_string(), output));
+            }
+        }
+    }
+    names
+}
+
+/// A variable a template reads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateVariable {
+    pub name: String,
+    /// Printed by a `{{ }}` tag, so it takes positional arguments. Variables
+    /// only used in `{% %}` statements, such as `{% if verbose %}`, are set
+    /// with `--set`.
+    pub output: bool,
+}
+
+/// Compiles a template command so syntax errors surface when it is saved.
+pub fn check_template(source: &str) -> Result<(), Box<dyn Error>> {
+    environment(Dialect::Posix).template_from_str(source)?;
+    Ok(())
+}
+
+/// Variables a template reads, in the order they first appear.
+pub fn template_variables(source: &str) -> Result<Vec<TemplateVariable>, Box<dyn Error>> {
+    let environment = environment(Dialect::Posix);
+    let globals: HashSet<&str> = environment.globals().map(|(name, _)| name).collect();
+    let undeclared = environment
+        .template_from_str(source)?
+        .undeclared_variables(false);
+
+    let names = tag_names(source);
+    let mut variables: Vec<(usize, TemplateVariable)> = undeclared
+        .into_iter()
+        .filter(|name| !globals.contains(name.as_str()))
+        .map(|name| {
+            let position = names
+                .iter()
+                .position(|(word, _)| *word == name)
+                .unwrap_or(usize::MAX);
+            let output = names.iter().any(|(word, output)| *word == name && *output);
+            (position, TemplateVariable { name, output })
+        })
+        .collect();
+    variables.sort_by(|a, b| (a.0, &a.1.name).cmp(&(b.0, &b.1.name)));
+
+    Ok(variables
+        .into_iter()
+        .map(|(_, variable)| variable)
+        .collect())
+}
+
+/// Resolves template variables the same way as placeholders, except that a
+/// variable without a value is left undefined so `{% if %}` blocks can skip it.
+/// Positional arguments only go to variables printed by `{{ }}` tags.
+/// Passing `--set name=value` more than once makes `name` a list.
+pub fn resolve_template_values<F>(
+    source: &str,
+    args: Vec<String>,
+    named: &[(String, String)],
+    lookup: F,
+) -> Result<Vec<Resolved>, Box<dyn Error>>
+where
+    F: Fn(&str) -> Result<Option<(String, Source)>, Box<dyn Error>>,
+{
+    let mut args = args.into_iter();
+    let mut resolved = Vec::new();
+
+    for TemplateVariable { name, output } in template_variables(source)? {
+        let values: Vec<String> = named
+            .iter()
+            .filter(|(key, _)| *key == name)
+            .map(|(_, value)| value.clone())
+            .collect();
+
+        let (values, source) = if !values.is_empty() {
+            (values, Source::Argument)
+        } else if let Some(value) = output.then(|| args.next()).flatten() {
+            (vec![value], Source::Argument)
+        } else if let Some((value, source)) = lookup(&name)? {
+            (vec![value], source)
+        } else {
+            continue;
+        };
+
+        resolved.push(Resolved {
+            name,
+            values,
+            source,
+        });
+    }
+
+    Ok(resolved)
+}
+
+/// Renders a template command with the resolved values, quoted for `dialect`.
+pub fn render_template(
+    source: &str,
+    resolved: &[Resolved],
+    dialect: Dialect,
+) -> Result<String, Box<dyn Error>> {
+    let context: BTreeMap<&str, Value> = resolved
+        .iter()
+        .map(|r| {
+            let value = match r.values.as_slice() {
+                [value] if value == "true" => Value::from(true),
+                [value] if value == "false" => Value::from(false),
+                [value] => Value::from(value.as_str()),
+                values => Value::from(values.to_vec()),
+            };
+            (r.name.as_str(), value)
+        })
+        .collect();
+
+    Ok(environment(dialect).render_str(&quote_outputs(source), context)?)
+}
@@ -0,0 +1,134 @@
+#[cfg(test)]
+mod tests {
+    use crate::placeholder::{Dialect, Source};
+    use crate::template::*;
+
+    fn named(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_template_conditionals_and_loops() {
+        let source = "curl {% if verbose %}-v {% endif %}{% for h in header %}-H {{ h }} {% endfor %}{{ url }}";
+        let variables: Vec<(String, bool)> = template_variables(source)
+            .unwrap()
+            .into_iter()
+            .map(|v| (v.name, v.output))
+            .collect();
+        assert_eq!(
+            variables,
+            [
+                ("verbose".to_string(), false),
+                ("header".to_string(), false),
+                ("url".to_string(), true)
+            ]
+        );
+
+        let set = named(&[
+            ("verbose", "true"),
+            ("header", "Accept: */*"),
+            ("header", "X-Id: 1"),
+        ]);
+        let resolved =
+            resolve_template_values(source, vec!["example.com".to_string()], &set, |_| Ok(None))
+                .unwrap();
+        assert_eq!(
+            render_template(source, &resolved, Dialect::Posix).unwrap(),
+            "curl -v -H 'Accept: */*' -H 'X-Id: 1' example.com"
+        );
+
+        let set = named(&[("url", "example.com")]);
+        let resolved = resolve_template_values(source, vec![], &set, |_| Ok(None)).unwrap();
+        assert_eq!(
+            render_template(source, &resolved, Dialect::Posix).unwrap(),
+            "curl example.com"
+        );
+    }
+
+    #[test]
+    fn test_positional_arguments_bind_to_output_variables() {
+        // The flag comes first but is only a condition, so the argument is the url
+        let source = "curl {% if verbose %}-v {% endif %}{{ url }}";
+        let resolved =
+            resolve_template_values(source, vec!["example.com".to_string()], &[], |_| Ok(None))
+                .unwrap();
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].name, "url");
+        assert_eq!(
+            render_template(source, &resolved, Dialect::Posix).unwrap(),
+            "curl example.com"
+        );
+
+        // Words outside tags do not count as a use of a variable
+        let source = "echo url: {{ host }}/{{ url }}";
+        let names: Vec<String> = template_variables(source)
+            .unwrap()
+            .into_iter()
+            .map(|v| v.name)
+            .collect();
+        assert_eq!(names, ["host", "url"]);
+    }
+
+    #[test]
+    fn test_template_values_quoted_for_context() {
+        let source = r#"curl -d "{{ body }}" -H '{{ header }}' {{ url }}"#;
+        let set = named(&[
+            ("body", r#"{"a": "$HOME"}"#),
+            ("header", "X-Name: it's"),
+            ("url", "example.com/a b"),
+        ]);
+        let resolved = resolve_template_values(source, vec![], &set, |_| Ok(None)).unwrap();
+        assert_eq!(
+            render_template(source, &resolved, Dialect::Posix).unwrap(),
+            r#"curl -d "{\"a\": \"\$HOME\"}" -H 'X-Name: it'\''s' 'example.com/a b'"#
+        );
+
+        let source = r#"echo "{{- greeting|safe -}}""#;
+        let set = named(&[("greeting", "$USER")]);
+        let resolved = resolve_template_values(source, vec![], &set, |_| Ok(None)).unwrap();
+        assert_eq!(
+            render_template(source, &resolved, Dialect::Posix).unwrap(),
+            r#"echo "$USER""#
+        );
+    }
+
+    #[test]
+    fn test_template_values_in_code() {
+        let source = r#"print("{{ msg }}", {{ count }})"#;
+        let set = named(&[("msg", "hi there"), ("count", "3")]);
+        let resolved = resolve_template_values(source, vec![], &set, |_| Ok(None)).unwrap();
+        assert_eq!(
+            render_template(source, &resolved, Dialect::Code).unwrap(),
+            r#"print("hi there", 3)"#
+        );
+
+        let set = named(&[("msg", r#"", __import__("os")"#), ("count", "3")]);
+        let resolved = resolve_template_values(source, vec![], &set, |_| Ok(None)).unwrap();
+        assert!(render_template(source, &resolved, Dialect::Code).is_err());
+    }
+
+    #[test]
+    fn test_template_values() {
+        let source = "ls {{ opts|safe }} {{ dir }}";
+        let resolved = resolve_template_values(source, vec![], &[], |name| match name {
+            "opts" => Ok(Some(("-la *.rs".to_string(), Source::GlobalVariable))),
+            "dir" => Ok(Some(("my dir".to_string(), Source::DotEnv))),
+            _ => Ok(None),
+        })
+        .unwrap();
+        assert_eq!(
+            render_template(source, &resolved, Dialect::Posix).unwrap(),
+            "ls -la *.rs 'my dir'"
+        );
+    }
+
+    #[test]
+    fn test_template_syntax_errors() {
+        assert!(check_template("echo {% if x %}yes").is_err());
+        assert!(check_template("echo {{ x }").is_err());
+        assert!(check_template("echo {% if x %}yes{% endif %}").is_ok());
+    }
+}
@@ -0,0 +1,142 @@
+use crate::placeholder::{placeholders, quote_value, Placeholder, Quote};
+use crate::template::template_variables;
+use colored::Colorize;
+use database::{Command, PlaceholderSpec};
+
+fn placeholder_kind(
+    placeholder: &Placeholder,
+    spec: Option<&PlaceholderSpec>,
+    template: bool,
+) -> String {
+    let mut kind = vec![match placeholder.variadic {
+        _ if template => "template variable, optional".to_string(),
+        _ if placeholder.is_function() => "built-in".to_string(),
+        _ if placeholder.name.starts_with("env.") => "environment".to_string(),
+        Some(0) => "variadic".to_string(),
+        Some(min) => format!("variadic, at least {}", min),
+        None => "argument".to_string(),
+    }];
+
+    if placeholder.raw {
+        kind.push("raw".to_string());
+    }
+    if let Some(ref default) = placeholder.default {
+        kind.push(format!("default: {}", default));
+    }
+    if let Some(generator) = spec.and_then(|spec| spec.generator.as_ref()) {
+        kind.push(format!("choices from: {}", generator));
+    }
+    kind.join(", ")
+}
+
+// How a placeholder is written on the `smriti run` line.
+fn usage_word(placeholder: &Placeholder, template: bool) -> String {
+    match (placeholder.variadic, &placeholder.default) {
+        _ if template => format!("[{}]", placeholder.name),
+        (Some(0), _) | (Some(_), Some(_)) => format!("[{}]...", placeholder.name),
+        (Some(_), None) => format!("<{}>...", placeholder.name),
+        (None, Some(_)) => format!("[{}]", placeholder.name),
+        (None, None) => format!("<{}>", placeholder.name),
+    }
+}
+
+/// Builds the help of a saved command from its placeholders.
+pub fn usage_text(command: &Command, specs: &[PlaceholderSpec]) -> String {
+    let template = command.template;
+    // Template variables are all optional and can be left undefined, and only
+    // the ones printed by `{{ }}` take positional arguments
+    let variables = match template {
+        true => template_variables(&command.command).unwrap_or_default(),
+        false => vec![],
+    };
+    let placeholders: Vec<Placeholder> = if template {
+        variables
+            .iter()
+            .map(|variable| Placeholder {
+                name: variable.name.clone(),
+                quote: Quote::None,
+                raw: false,
+                default: None,
+                variadic: None,
+            })
+            .collect()
+    } else {
+        placeholders(&command.command)
+    };
+    let set_only: Vec<&str> = variables
+        .iter()
+        .filter(|variable| !variable.output)
+        .map(|variable| variable.name.as_str())
+        .collect();
+    let positional: Vec<&Placeholder> = placeholders
+        .iter()
+        .filter(|p| p.is_positional() && !set_only.contains(&p.name.as_str()))
+        .collect();
+
+    let mut usage = vec![format!("smriti run {}", command.alias)];
+    usage.extend(positional.iter().map(|p| usage_word(p, template)));
+
+    let mut text = format!("{} {}\n", "Usage:".cyan().bold(), usage.join(" "));
+    if !command.info.is_empty() {
+        text.push_str(&format!("\n{}\n", command.info));
+    }
+    text.push_str(&format!(
+        "\n{}\n  {}\n",
+        "Command:".cyan().bold(),
+        command.command
+    ));
+
+    if !placeholders.is_empty() {
+        let rows: Vec<(String, String, String)> = placeholders
+            .iter()
+            .map(|placeholder| {
+                let spec = specs.iter().find(|spec| spec.name == placeholder.name);
+                let name = match placeholder.variadic {
+                    Some(_) => format!("{}...", placeholder.name),
+                    None => placeholder.name.clone(),
+                };
+                let description = spec
+                    .and_then(|spec| spec.description.clone())
+                    .unwrap_or_default();
+                let mut kind = placeholder_kind(placeholder, spec, template);
+                if set_only.contains(&placeholder.name.as_str()) {
+                    kind.push_str(&format!(", --set {}=...", placeholder.name));
+                }
+                (name, kind, description)
+            })
+            .collect();
+
+        let name_width = rows.iter().map(|row| row.0.len()).max().unwrap_or(0);
+        let kind_width = rows.iter().map(|row| row.1.len()).max().unwrap_or(0);
+
+        text.push_str(&format!("\n{}\n", "Placeholders:".cyan().bold()));
+        for (name, kind, description) in rows {
+            let line = format!(
+                "  {:<name_width$}  {:<kind_width$}  {}",
+                name, kind, description
+            );
+            text.push_str(&format!("{}\n", line.trim_end()));
+        }
+    }
+
+    // Fill in defaults where there are any so the example shows them, quoted
+    // so the line can be run as it is
+    let mut example = vec![format!("smriti run {}", command.alias)];
+    example.extend(
+        positional
+            .iter()
+            .filter_map(|p| match (&p.default, p.variadic) {
+                (Some(default), _) => Some(quote_value(default, Quote::None)),
+                (None, Some(0)) => None,
+                (None, _) => Some(format!("<{}>", p.name)),
+            }),
+    );
+    example.extend(set_only.iter().map(|name| format!("--set {}=...", name)));
+    text.push_str(&format!(
+        "\n{}\n  {}\n",
+        "Example:".cyan().bold(),
+        example.join(" ")
+    ));
+
+    text
+}
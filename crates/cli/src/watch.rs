@@ -0,0 +1,219 @@
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{Match, WalkBuilder};
+use notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+// How long events for a path are gathered before they are reported, so a file
+// written in several steps counts as one change.
+const EVENT_DELAY: Duration = Duration::from_millis(50);
+
+/// Matches `/`-separated paths against globs such as `src/**/*.rs` or
+/// `*.{js,ts}`. A glob without a `/` matches the file name in any directory.
+pub fn glob_set(globs: &[String]) -> Result<GlobSet, Box<dyn Error>> {
+    let mut set = GlobSetBuilder::new();
+    for glob in globs {
+        let glob = match glob.contains('/') {
+            true => glob.clone(),
+            false => format!("**/{}", glob),
+        };
+        set.add(GlobBuilder::new(&glob).literal_separator(true).build()?);
+    }
+    Ok(set.build()?)
+}
+
+// The rules of a file written like a `.gitignore`, for paths under `dir`.
+fn gitignore(dir: &Path, file: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(dir);
+    builder.add(file);
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+/// The paths git leaves out: those ignored by `.gitignore` files in and above
+/// the watched directory, by `.git/info/exclude` and by the global excludes
+/// file, and `.git` itself. Paths are relative to the watched directory.
+pub struct Ignore {
+    root: PathBuf,
+    // The top of the repository, or the watched directory outside of one
+    top: PathBuf,
+    gitignores: HashMap<PathBuf, Gitignore>,
+    exclude: Gitignore,
+    global: Gitignore,
+}
+
+impl Ignore {
+    pub fn new(root: &Path) -> Ignore {
+        let top = root
+            .ancestors()
+            .find(|dir| dir.join(".git").exists())
+            .unwrap_or(root)
+            .to_path_buf();
+        let mut ignore = Ignore {
+            root: root.to_path_buf(),
+            exclude: gitignore(&top, &top.join(".git").join("info").join("exclude")),
+            global: GitignoreBuilder::new(&top).build_global().0,
+            top,
+            gitignores: HashMap::new(),
+        };
+        let dirs: Vec<PathBuf> = root
+            .ancestors()
+            .take_while(|dir| dir.starts_with(&ignore.top))
+            .map(Path::to_path_buf)
+            .collect();
+        for dir in dirs {
+            ignore.read_gitignore(&dir);
+        }
+        ignore
+    }
+
+    /// Reads the `.gitignore` in `dir` again, or forgets it when it is gone.
+    pub fn add_dir(&mut self, dir: &Path) {
+        self.read_gitignore(&self.root.join(dir));
+    }
+
+    fn read_gitignore(&mut self, dir: &Path) {
+        let file = dir.join(".gitignore");
+        if file.is_file() {
+            self.gitignores
+                .insert(dir.to_path_buf(), gitignore(dir, &file));
+        } else {
+            self.gitignores.remove(dir);
+        }
+    }
+
+    /// Whether git ignores the path. The `.gitignore` closest to it decides
+    /// first, then the ones above it, `.git/info/exclude` and the global file.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if path.components().any(|part| part.as_os_str() == ".git") {
+            return true;
+        }
+        let path = self.root.join(path);
+        let gitignores = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.top))
+            .filter_map(|dir| self.gitignores.get(dir));
+        for gitignore in gitignores.chain([&self.exclude, &self.global]) {
+            match gitignore.matched_path_or_any_parents(&path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+}
+
+/// Watches a directory tree for changes to files matching any of the globs,
+/// leaving out what git ignores.
+pub struct Watcher {
+    root: PathBuf,
+    globs: GlobSet,
+    ignore: Ignore,
+    debouncer: Debouncer<RecommendedWatcher>,
+    events: mpsc::Receiver<DebounceEventResult>,
+}
+
+impl Watcher {
+    pub fn new(root: &Path, globs: &[String]) -> Result<Watcher, Box<dyn Error>> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = Watcher {
+            root: root.to_path_buf(),
+            globs: glob_set(globs)?,
+            ignore: Ignore::new(root),
+            debouncer: new_debouncer(EVENT_DELAY, sender)?,
+            events,
+        };
+        watcher.add_dir(Path::new(""))?;
+        Ok(watcher)
+    }
+
+    // Starts watching a directory and the directories below it that git does
+    // not ignore. Ignored directories such as `target` are not watched at all.
+    fn add_dir(&mut self, dir: &Path) -> Result<(), Box<dyn Error>> {
+        // Symbolic links are not followed, they may point out of the tree or into a loop
+        let walk = WalkBuilder::new(self.root.join(dir))
+            .hidden(false)
+            .ignore(false)
+            .require_git(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build();
+        for entry in walk.flatten() {
+            if !entry.file_type().is_some_and(|kind| kind.is_dir()) {
+                continue;
+            }
+            if let Ok(dir) = entry.path().strip_prefix(&self.root) {
+                self.ignore.add_dir(dir);
+            }
+            self.debouncer
+                .watcher()
+                .watch(entry.path(), RecursiveMode::NonRecursive)?;
+        }
+        Ok(())
+    }
+
+    /// Waits for a matching file to change, for at most `timeout` when given,
+    /// and returns its path relative to the watched directory.
+    pub fn changed(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<Option<PathBuf>, Box<dyn Error>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let events = match deadline {
+                Some(deadline) => {
+                    match self
+                        .events
+                        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                    {
+                        Ok(events) => events,
+                        Err(mpsc::RecvTimeoutError::Timeout) => return Ok(None),
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+                None => self.events.recv()?,
+            };
+            let Ok(events) = events else {
+                // Events were lost, anything may have changed
+                return Ok(Some(PathBuf::from(".")));
+            };
+
+            let mut changed = None;
+            for event in events {
+                let Ok(path) = event.path.strip_prefix(&self.root) else {
+                    continue;
+                };
+                let path = path.to_path_buf();
+                if path.file_name().is_some_and(|name| name == ".gitignore") {
+                    self.ignore.add_dir(path.parent().unwrap_or(Path::new("")));
+                }
+                let is_dir = fs::symlink_metadata(&event.path).is_ok_and(|meta| meta.is_dir());
+                if self.ignore.is_ignored(&path, is_dir) {
+                    continue;
+                }
+                if is_dir {
+                    // New directories are watched too; removed ones fail to be
+                    let _ = self.add_dir(&path);
+                } else if changed.is_none() && self.globs.is_match(&path) {
+                    changed = Some(path);
+                }
+            }
+            if changed.is_some() {
+                return Ok(changed);
+            }
+        }
+    }
+
+    /// Waits until no matching file has changed for `quiet`, as saving or
+    /// switching branches often changes many files in a row.
+    pub fn settle(&mut self, quiet: Duration) -> Result<(), Box<dyn Error>> {
+        while self.changed(Some(quiet))?.is_some() {}
+        Ok(())
+    }
+}
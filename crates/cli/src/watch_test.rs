@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod tests {
+    use crate::watch::*;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_globs() {
+        let matches = |glob: &str, path: &str| {
+            glob_set(&[glob.to_string()])
+                .unwrap()
+                .is_match(Path::new(path))
+        };
+        assert!(matches("src/**/*.rs", "src/main.rs"));
+        assert!(matches("src/**/*.rs", "src/a/b/lib.rs"));
+        assert!(!matches("src/*.rs", "src/a/lib.rs"));
+        assert!(matches("*.py", "app/models/user.py"));
+        assert!(matches("*.{js,ts}", "app.ts"));
+        assert!(matches("log?.[!a-c]xt", "log1.txt"));
+        assert!(!matches("log?.[!a-c]xt", "log1.bxt"));
+        assert!(matches("a+b(1).txt", "a+b(1).txt"));
+        assert!(glob_set(&["*.{js".to_string()]).is_err());
+        assert!(glob_set(&["[ab".to_string()]).is_err());
+    }
+
+    // A directory with the given files, removed when the test ends.
+    struct Tree(PathBuf);
+
+    impl Tree {
+        fn new(files: &[(&str, &str)]) -> Tree {
+            let root = std::env::temp_dir().join(format!("smriti-watch-{}", uuid::Uuid::new_v4()));
+            for (path, text) in files {
+                let path = root.join(path);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, text).unwrap();
+            }
+            Tree(root)
+        }
+    }
+
+    impl Drop for Tree {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_gitignore_rules() {
+        let tree = Tree::new(&[
+            (".git/info/exclude", "*.swp\n"),
+            (
+                ".gitignore",
+                "# build output\ntarget/\n*.log\n!keep.log\n/docs\n**/cache/**\nlogs/**/*.gz\n",
+            ),
+            ("web/.gitignore", "dist\n!debug.log\n"),
+        ]);
+        let mut ignore = Ignore::new(&tree.0);
+        ignore.add_dir(Path::new("web"));
+        let ignored = |path: &str, is_dir: bool| ignore.is_ignored(Path::new(path), is_dir);
+
+        assert!(ignored("target", true));
+        assert!(ignored("target/debug/smriti", false));
+        assert!(!ignored("target", false));
+        assert!(ignored("src/debug.log", false));
+        assert!(!ignored("src/keep.log", false));
+        assert!(ignored("docs/index.md", false));
+        assert!(!ignored("src/docs", true));
+        assert!(ignored(".git/HEAD", false));
+        assert!(!ignored("src/main.rs", false));
+
+        // `**` matches any number of directories, but not none after a `/`
+        assert!(ignored("a/cache/b/c.txt", false));
+        assert!(ignored("logs/2024/01/app.gz", false));
+        assert!(ignored("logs/app.gz", false));
+        assert!(!ignored("logs/app.txt", false));
+
+        // Nested files apply below their directory and win over the ones above
+        assert!(ignored("web/dist/app.js", false));
+        assert!(!ignored("dist/app.js", false));
+        assert!(!ignored("web/debug.log", false));
+        assert!(ignored("api/debug.log", false));
+
+        // .git/info/exclude applies like a .gitignore at the top
+        assert!(ignored("src/.main.rs.swp", false));
+
+        // A subdirectory of the repository still gets the rules above it
+        let ignore = Ignore::new(&tree.0.join("web"));
+        assert!(ignore.is_ignored(Path::new("dist/app.js"), false));
+        assert!(ignore.is_ignored(Path::new("src/.app.js.swp"), false));
+        assert!(ignore.is_ignored(Path::new("target"), true));
+        assert!(!ignore.is_ignored(Path::new("debug.log"), false));
+    }
+
+    #[test]
+    fn test_watcher() {
+        let root = std::env::temp_dir().join(format!("smriti-watch-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+
+        let mut watcher = Watcher::new(&root, &["*.rs".to_string()]).unwrap();
+        assert_eq!(
+            watcher.changed(Some(Duration::from_millis(50))).unwrap(),
+            None
+        );
+
+        let writer = {
+            let root = root.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                fs::write(root.join("target/build.rs"), "").unwrap();
+                fs::write(root.join("src/notes.txt"), "").unwrap();
+                fs::create_dir(root.join("src/new")).unwrap();
+                thread::sleep(Duration::from_millis(600));
+                fs::write(root.join("src/new/lib.rs"), "").unwrap();
+            })
+        };
+        let changed = watcher.changed(Some(Duration::from_secs(5))).unwrap();
+        writer.join().unwrap();
+        assert_eq!(changed.as_deref(), Some(Path::new("src/new/lib.rs")));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
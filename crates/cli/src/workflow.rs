@@ -0,0 +1,126 @@
+use crate::placeholder::{parse_command, render_value, Resolved, Segment};
+use std::error::Error;
+
+/// One step of a workflow: a saved alias and the arguments passed to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub alias: String,
+    /// Arguments, which may use the placeholders of the workflow.
+    pub args: Vec<String>,
+    /// Written with a leading `-`, like in a Makefile: a failure does not stop the workflow.
+    pub continue_on_error: bool,
+}
+
+// Splits a workflow body into steps of words. Steps end at `;` or a new line
+// and words at whitespace, except inside quotes or placeholder braces.
+fn split_steps(body: &str) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+    let mut steps = vec![];
+    let mut words: Vec<String> = vec![];
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut braces = 0;
+
+    for c in body.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') if braces == 0 => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, ';' | '\n') if braces == 0 => {
+                words.extend(word.take());
+                if !words.is_empty() {
+                    steps.push(std::mem::take(&mut words));
+                }
+            }
+            (None, c) if c.is_whitespace() && braces == 0 => words.extend(word.take()),
+            (None, c) => {
+                match c {
+                    '{' => braces += 1,
+                    '}' if braces > 0 => braces -= 1,
+                    _ => {}
+                }
+                word.get_or_insert_with(String::new).push(c);
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err("Unterminated quote in workflow".into());
+    }
+    words.extend(word.take());
+    if !words.is_empty() {
+        steps.push(words);
+    }
+    Ok(steps)
+}
+
+/// Parses a workflow body such as `test; -lint; build; push {tag}` into its steps.
+pub fn parse_steps(body: &str) -> Result<Vec<Step>, Box<dyn Error>> {
+    let steps = split_steps(body)?
+        .into_iter()
+        .map(|mut words| {
+            let first = words.remove(0);
+            let (alias, continue_on_error) = match first.strip_prefix('-') {
+                Some(alias) => (alias.to_string(), true),
+                None => (first, false),
+            };
+            if alias.is_empty() {
+                return Err("Workflow step without an alias".into());
+            }
+            Ok(Step {
+                alias,
+                args: words,
+                continue_on_error,
+            })
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+    if steps.is_empty() {
+        return Err("Workflow has no steps".into());
+    }
+    Ok(steps)
+}
+
+/// Finds aliases that run each other in a loop starting from `alias`, given
+/// the step aliases of every workflow. Returns the loop, e.g. `[a, b, a]`.
+pub fn find_cycle<F>(alias: &str, steps_of: F) -> Option<Vec<String>>
+where
+    F: Fn(&str) -> Option<Vec<String>>,
+{
+    fn visit<F>(alias: &str, steps_of: &F, path: &mut Vec<String>) -> Option<Vec<String>>
+    where
+        F: Fn(&str) -> Option<Vec<String>>,
+    {
+        if let Some(start) = path.iter().position(|seen| seen == alias) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(alias.to_string());
+            return Some(cycle);
+        }
+        path.push(alias.to_string());
+        for step in steps_of(alias).unwrap_or_default() {
+            if let Some(cycle) = visit(&step, steps_of, path) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        None
+    }
+
+    visit(alias, &steps_of, &mut vec![])
+}
+
+/// Fills the workflow's values into the arguments of a step. An argument that
+/// is a single variadic placeholder becomes one argument per value.
+pub fn render_args(args: &[String], resolved: &[Resolved]) -> Vec<String> {
+    args.iter()
+        .flat_map(|arg| match parse_command(arg).as_slice() {
+            [Segment::Placeholder(placeholder)] if placeholder.variadic.is_some() => resolved
+                .iter()
+                .find(|r| r.name == placeholder.name)
+                .map(|r| r.values.clone())
+                .unwrap_or_default(),
+            _ => vec![render_value(arg, resolved)],
+        })
+        .collect()
+}
@@ -0,0 +1,57 @@
+#[cfg(test)]
+mod tests {
+    use crate::placeholder::{resolve_placeholders, Source};
+    use crate::workflow::*;
+
+    #[test]
+    fn test_parse_steps() {
+        let steps =
+            parse_steps("test; -lint\nnotify 'release {tag} done'; push {tag=v1 beta}").unwrap();
+        assert_eq!(steps.len(), 4);
+        assert_eq!(steps[0].alias, "test");
+        assert!(steps[1].continue_on_error && !steps[0].continue_on_error);
+        assert_eq!(steps[2].args, vec!["release {tag} done"]);
+        assert_eq!(steps[3].args, vec!["{tag=v1 beta}"]);
+
+        assert!(parse_steps(" ; \n").is_err());
+        assert!(parse_steps("test; -").is_err());
+        assert!(parse_steps("notify 'oops").is_err());
+    }
+
+    #[test]
+    fn test_find_cycle() {
+        let steps_of = |alias: &str| match alias {
+            "release" => Some(vec!["test".to_string(), "deploy".to_string()]),
+            "deploy" => Some(vec!["push".to_string(), "release".to_string()]),
+            _ => None,
+        };
+        assert_eq!(
+            find_cycle("release", steps_of),
+            Some(vec![
+                "release".to_string(),
+                "deploy".to_string(),
+                "release".to_string()
+            ])
+        );
+        assert_eq!(find_cycle("test", steps_of), None);
+    }
+
+    #[test]
+    fn test_render_args() {
+        let body = "push {tag}; copy {files...}";
+        let args = vec![
+            "v1.2 final".to_string(),
+            "a.txt".to_string(),
+            "b.txt".to_string(),
+        ];
+        let resolved = resolve_placeholders(body, args, &[], None, |_| Ok(None)).unwrap();
+        assert_eq!(resolved[0].source, Source::Argument);
+
+        let steps = parse_steps(body).unwrap();
+        assert_eq!(render_args(&steps[0].args, &resolved), vec!["v1.2 final"]);
+        assert_eq!(
+            render_args(&steps[1].args, &resolved),
+            vec!["a.txt", "b.txt"]
+        );
+    }
+}
@@ -10,16 +10,161 @@ pub fn create_conn() -> Result<Connection> {
     let conn = Connection::open(db_path)?;
 
     // Create the necessary tables if they do not exist
+    create_tables(&conn)?;
+
+    Ok(conn) // Return the connection
+}
+
+pub fn create_tables(conn: &Connection) -> Result<()> {
+    // Child tables are cleaned up with their command
+    conn.execute_batch("PRAGMA foreign_keys = ON")?;
+
     conn.execute(
         "CREATE TABLE IF NOT EXISTS commands (
              id INTEGER PRIMARY KEY,
              command TEXT NOT NULL UNIQUE,
              alias TEXT UNIQUE,
              info TEXT,
-             service TEXT
+             service TEXT,
+             template INTEGER NOT NULL DEFAULT 0,
+             interactive INTEGER NOT NULL DEFAULT 0,
+             shell TEXT,
+             cwd TEXT,
+             clear_env INTEGER NOT NULL DEFAULT 0,
+             confirm INTEGER NOT NULL DEFAULT 0,
+             timeout_ms INTEGER,
+             retries INTEGER NOT NULL DEFAULT 0,
+             retry_delay_ms INTEGER,
+             workflow INTEGER NOT NULL DEFAULT 0,
+             script INTEGER NOT NULL DEFAULT 0
         )",
         [],
     )?;
+    add_column(conn, "commands", "template", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column(
+        conn,
+        "commands",
+        "interactive",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column(conn, "commands", "shell", "TEXT")?;
+    add_column(conn, "commands", "cwd", "TEXT")?;
+    add_column(conn, "commands", "clear_env", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column(conn, "commands", "confirm", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column(conn, "commands", "timeout_ms", "INTEGER")?;
+    add_column(conn, "commands", "retries", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column(conn, "commands", "retry_delay_ms", "INTEGER")?;
+    add_column(conn, "commands", "workflow", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column(conn, "commands", "script", "INTEGER NOT NULL DEFAULT 0")?;
 
-    Ok(conn) // Return the connection
+    // Global variables are stored with an empty service
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS variables (
+             id INTEGER PRIMARY KEY,
+             name TEXT NOT NULL,
+             value TEXT NOT NULL,
+             service TEXT NOT NULL DEFAULT '',
+             UNIQUE (name, service)
+        )",
+        [],
+    )?;
+
+    // smriti's own settings, such as the default shell
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS settings (
+             key TEXT PRIMARY KEY,
+             value TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Per-placeholder settings of a saved command
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS placeholders (
+             id INTEGER PRIMARY KEY,
+             command_id INTEGER NOT NULL REFERENCES commands (id) ON DELETE CASCADE,
+             name TEXT NOT NULL,
+             generator TEXT,
+             generator_column INTEGER,
+             description TEXT,
+             UNIQUE (command_id, name)
+        )",
+        [],
+    )?;
+    add_column(conn, "placeholders", "description", "TEXT")?;
+
+    // Environment variables set when a saved command runs
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS command_env (
+             id INTEGER PRIMARY KEY,
+             command_id INTEGER NOT NULL REFERENCES commands (id) ON DELETE CASCADE,
+             name TEXT NOT NULL,
+             value TEXT NOT NULL,
+             UNIQUE (command_id, name)
+        )",
+        [],
+    )?;
+
+    // Values passed to placeholders in earlier runs, offered again as suggestions
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS placeholder_history (
+             id INTEGER PRIMARY KEY,
+             command_id INTEGER NOT NULL REFERENCES commands (id) ON DELETE CASCADE,
+             name TEXT NOT NULL,
+             value TEXT NOT NULL,
+             uses INTEGER NOT NULL DEFAULT 1,
+             last_used INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+             UNIQUE (command_id, name, value)
+        )",
+        [],
+    )?;
+
+    // Executions of saved commands; output is only kept for captured runs
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS runs (
+             id INTEGER PRIMARY KEY,
+             command_id INTEGER NOT NULL REFERENCES commands (id) ON DELETE CASCADE,
+             command TEXT NOT NULL,
+             started_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+             duration_ms INTEGER NOT NULL,
+             exit_code INTEGER,
+             output TEXT,
+             attempt INTEGER NOT NULL DEFAULT 1
+        )",
+        [],
+    )?;
+    add_column(conn, "runs", "attempt", "INTEGER NOT NULL DEFAULT 1")?;
+
+    // Saved commands run by `smriti scheduler`, one schedule per command.
+    // last_run is the last scheduled time that was run or skipped.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schedules (
+             id INTEGER PRIMARY KEY,
+             command_id INTEGER NOT NULL UNIQUE REFERENCES commands (id) ON DELETE CASCADE,
+             cron TEXT NOT NULL,
+             missed TEXT NOT NULL DEFAULT 'skip',
+             last_run INTEGER,
+             created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+// Adds a column to a table created by an older version of smriti.
+fn add_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(Result::ok)
+        .any(|name| name == column);
+
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
+    Ok(())
 }
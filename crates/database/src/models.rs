@@ -5,4 +5,96 @@ pub struct Command {
     pub alias: String,
     pub info: String,
     pub service: String,
+    /// Rendered with the template engine instead of `{placeholder}` substitution
+    pub template: bool,
+    /// Runs attached to the terminal and is never captured
+    pub interactive: bool,
+    /// Interpreter to run the command with instead of the default shell
+    pub shell: Option<String>,
+    /// Directory to run in, may start with `~` and contain `$VARIABLES`
+    pub cwd: Option<String>,
+    /// Runs without inheriting smriti's environment
+    pub clear_env: bool,
+    /// Asks for confirmation before it runs
+    pub confirm: bool,
+    /// Stops the command after this many milliseconds
+    pub timeout_ms: Option<i64>,
+    /// Runs the command again this many times when it fails
+    pub retries: i64,
+    /// Wait before the first retry, doubled before each following one
+    pub retry_delay_ms: Option<i64>,
+    /// The command is a list of other aliases to run in order
+    pub workflow: bool,
+    /// The command is a multi-line script, run from a file
+    pub script: bool,
+}
+
+#[derive(Debug)]
+pub struct Variable {
+    pub id: i64,
+    pub name: String,
+    pub value: String,
+    pub service: String,
+}
+
+#[derive(Debug)]
+pub struct Setting {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug)]
+pub struct PlaceholderSpec {
+    pub id: i64,
+    pub command_id: i64,
+    pub name: String,
+    pub generator: Option<String>,
+    pub generator_column: Option<i64>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct CommandEnv {
+    pub id: i64,
+    pub command_id: i64,
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug)]
+pub struct PlaceholderValue {
+    pub id: i64,
+    pub command_id: i64,
+    pub name: String,
+    pub value: String,
+    pub uses: i64,
+    pub last_used: String,
+}
+
+#[derive(Debug)]
+pub struct Run {
+    pub id: i64,
+    pub command_id: i64,
+    pub alias: String,
+    pub command: String,
+    pub started_at: String,
+    pub duration_ms: i64,
+    pub exit_code: Option<i32>,
+    pub output: Option<String>,
+    /// 1 for the first try, 2 for the first retry and so on
+    pub attempt: i64,
+}
+
+#[derive(Debug)]
+pub struct Schedule {
+    pub id: i64,
+    pub command_id: i64,
+    pub alias: String,
+    pub cron: String,
+    /// What to do with runs missed while the scheduler was not running: skip, once or all
+    pub missed: String,
+    /// Unix time of the last scheduled run that was run or skipped
+    pub last_run: Option<i64>,
+    /// Unix time the schedule was added
+    pub created_at: i64,
 }
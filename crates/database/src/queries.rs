@@ -1,5 +1,32 @@
-use crate::models::Command;
-use rusqlite::{params, Connection, Result};
+use crate::models::{
+    Command, CommandEnv, PlaceholderSpec, PlaceholderValue, Run, Schedule, Setting, Variable,
+};
+use rusqlite::{params, Connection, Result, Row};
+
+const COMMAND_COLUMNS: &str = "id, command, alias, info, service, template, interactive, shell, \
+                               cwd, clear_env, confirm, timeout_ms, retries, retry_delay_ms, \
+                               workflow, script";
+
+fn command_from_row(row: &Row) -> Result<Command> {
+    Ok(Command {
+        id: row.get(0)?,
+        command: row.get(1)?,
+        alias: row.get(2)?,
+        info: row.get(3)?,
+        service: row.get(4)?,
+        template: row.get(5)?,
+        interactive: row.get(6)?,
+        shell: row.get(7)?,
+        cwd: row.get(8)?,
+        clear_env: row.get(9)?,
+        confirm: row.get(10)?,
+        timeout_ms: row.get(11)?,
+        retries: row.get(12)?,
+        retry_delay_ms: row.get(13)?,
+        workflow: row.get(14)?,
+        script: row.get(15)?,
+    })
+}
 
 pub fn insert_command(
     conn: &Connection,
@@ -16,17 +43,10 @@ pub fn insert_command(
 }
 
 pub fn display_commands(conn: &Connection) -> Result<Vec<Command>, rusqlite::Error> {
-    let mut stmt = conn.prepare("SELECT * FROM commands")?;
+    let query = format!("SELECT {} FROM commands", COMMAND_COLUMNS);
+    let mut stmt = conn.prepare(&query)?;
 
-    let data_iter = stmt.query_map([], |row| {
-        Ok(Command {
-            id: row.get(0)?,
-            command: row.get(1)?,
-            alias: row.get(2)?,
-            info: row.get(3)?,
-            service: row.get(4)?,
-        })
-    })?;
+    let data_iter = stmt.query_map([], command_from_row)?;
 
     // Collect the iterator into a Vec<Command>
     let commands: Vec<Command> = data_iter.filter_map(Result::ok).collect();
@@ -62,17 +82,9 @@ pub fn retrieve_command_by_alias(
     conn: &Connection,
     alias: &str,
 ) -> Result<Command, rusqlite::Error> {
-    let query = "SELECT id, command, alias, info, service FROM commands WHERE alias = ?1";
-    let mut stmt = conn.prepare(query)?;
-    let command = stmt.query_row([alias], |row| {
-        Ok(Command {
-            id: row.get(0)?,
-            command: row.get(1)?,
-            alias: row.get(2)?,
-            info: row.get(3)?,
-            service: row.get(4)?,
-        })
-    })?;
+    let query = format!("SELECT {} FROM commands WHERE alias = ?1", COMMAND_COLUMNS);
+    let mut stmt = conn.prepare(&query)?;
+    let command = stmt.query_row([alias], command_from_row)?;
 
     Ok(command)
 }
@@ -81,18 +93,13 @@ pub fn retrieve_commands_by_service(
     conn: &Connection,
     service: &str,
 ) -> Result<Vec<Command>, rusqlite::Error> {
-    let query = "SELECT id, command, alias, info, service FROM commands WHERE service = ?1";
-    let mut stmt = conn.prepare(query)?;
+    let query = format!(
+        "SELECT {} FROM commands WHERE service = ?1 ORDER BY id",
+        COMMAND_COLUMNS
+    );
+    let mut stmt = conn.prepare(&query)?;
 
-    let data_iter = stmt.query_map([service], |row| {
-        Ok(Command {
-            id: row.get(0)?,
-            command: row.get(1)?,
-            alias: row.get(2)?,
-            info: row.get(3)?,
-            service: row.get(4)?,
-        })
-    })?;
+    let data_iter = stmt.query_map([service], command_from_row)?;
 
     // Collect the iterator into a Vec<Command>
     let commands: Vec<Command> = data_iter.filter_map(Result::ok).collect();
@@ -142,8 +149,504 @@ pub fn update_info_by_alias(
     Ok(())
 }
 
+pub fn update_template_by_alias(
+    conn: &Connection,
+    alias: &str,
+    value: bool,
+) -> Result<(), rusqlite::Error> {
+    let query = "UPDATE commands SET template = ?2 WHERE alias = ?1";
+    conn.execute(query, params![alias, value])?;
+    Ok(())
+}
+
+pub fn update_interactive_by_alias(
+    conn: &Connection,
+    alias: &str,
+    value: bool,
+) -> Result<(), rusqlite::Error> {
+    let query = "UPDATE commands SET interactive = ?2 WHERE alias = ?1";
+    conn.execute(query, params![alias, value])?;
+    Ok(())
+}
+
+pub fn update_shell_by_alias(
+    conn: &Connection,
+    alias: &str,
+    value: Option<&str>,
+) -> Result<(), rusqlite::Error> {
+    let query = "UPDATE commands SET shell = ?2 WHERE alias = ?1";
+    conn.execute(query, params![alias, value])?;
+    Ok(())
+}
+
+pub fn update_cwd_by_alias(
+    conn: &Connection,
+    alias: &str,
+    value: Option<&str>,
+) -> Result<(), rusqlite::Error> {
+    let query = "UPDATE commands SET cwd = ?2 WHERE alias = ?1";
+    conn.execute(query, params![alias, value])?;
+    Ok(())
+}
+
+pub fn update_clear_env_by_alias(
+    conn: &Connection,
+    alias: &str,
+    value: bool,
+) -> Result<(), rusqlite::Error> {
+    let query = "UPDATE commands SET clear_env = ?2 WHERE alias = ?1";
+    conn.execute(query, params![alias, value])?;
+    Ok(())
+}
+
+pub fn update_confirm_by_alias(
+    conn: &Connection,
+    alias: &str,
+    value: bool,
+) -> Result<(), rusqlite::Error> {
+    let query = "UPDATE commands SET confirm = ?2 WHERE alias = ?1";
+    conn.execute(query, params![alias, value])?;
+    Ok(())
+}
+
+pub fn update_timeout_by_alias(
+    conn: &Connection,
+    alias: &str,
+    value: Option<i64>,
+) -> Result<(), rusqlite::Error> {
+    let query = "UPDATE commands SET timeout_ms = ?2 WHERE alias = ?1";
+    conn.execute(query, params![alias, value])?;
+    Ok(())
+}
+
+pub fn update_retries_by_alias(
+    conn: &Connection,
+    alias: &str,
+    value: i64,
+) -> Result<(), rusqlite::Error> {
+    let query = "UPDATE commands SET retries = ?2 WHERE alias = ?1";
+    conn.execute(query, params![alias, value])?;
+    Ok(())
+}
+
+pub fn update_retry_delay_by_alias(
+    conn: &Connection,
+    alias: &str,
+    value: Option<i64>,
+) -> Result<(), rusqlite::Error> {
+    let query = "UPDATE commands SET retry_delay_ms = ?2 WHERE alias = ?1";
+    conn.execute(query, params![alias, value])?;
+    Ok(())
+}
+
+pub fn update_workflow_by_alias(
+    conn: &Connection,
+    alias: &str,
+    value: bool,
+) -> Result<(), rusqlite::Error> {
+    let query = "UPDATE commands SET workflow = ?2 WHERE alias = ?1";
+    conn.execute(query, params![alias, value])?;
+    Ok(())
+}
+
+pub fn update_script_by_alias(
+    conn: &Connection,
+    alias: &str,
+    value: bool,
+) -> Result<(), rusqlite::Error> {
+    let query = "UPDATE commands SET script = ?2 WHERE alias = ?1";
+    conn.execute(query, params![alias, value])?;
+    Ok(())
+}
+
 pub fn rename_alias(conn: &Connection, alias: &str, value: &str) -> Result<(), rusqlite::Error> {
     let query = "UPDATE commands SET alias = ?2 WHERE alias = ?1";
     conn.execute(query, [alias, value])?;
     Ok(())
 }
+
+pub fn set_variable(
+    conn: &Connection,
+    name: &str,
+    value: &str,
+    service: Option<&str>,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO variables (name, value, service) VALUES (?1, ?2, ?3)
+         ON CONFLICT (name, service) DO UPDATE SET value = excluded.value",
+        params![name, value, service.unwrap_or("")],
+    )?;
+    Ok(())
+}
+
+pub fn get_variable(
+    conn: &Connection,
+    name: &str,
+    service: Option<&str>,
+) -> Result<String, rusqlite::Error> {
+    let query = "SELECT value FROM variables WHERE name = ?1 AND service = ?2";
+    let mut stmt = conn.prepare(query)?;
+    let value = stmt.query_row([name, service.unwrap_or("")], |row| row.get(0))?;
+    Ok(value)
+}
+
+pub fn list_variables(
+    conn: &Connection,
+    service: Option<&str>,
+) -> Result<Vec<Variable>, rusqlite::Error> {
+    let mut stmt = match service {
+        Some(_) => conn.prepare(
+            "SELECT id, name, value, service FROM variables WHERE service = ?1 ORDER BY name",
+        )?,
+        None => {
+            conn.prepare("SELECT id, name, value, service FROM variables ORDER BY service, name")?
+        }
+    };
+
+    let data_iter = stmt.query_map(rusqlite::params_from_iter(service.iter()), |row| {
+        Ok(Variable {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            value: row.get(2)?,
+            service: row.get(3)?,
+        })
+    })?;
+
+    let variables: Vec<Variable> = data_iter.filter_map(Result::ok).collect();
+
+    Ok(variables)
+}
+
+pub fn unset_variable(
+    conn: &Connection,
+    name: &str,
+    service: Option<&str>,
+) -> Result<usize, rusqlite::Error> {
+    let query = "DELETE FROM variables WHERE name = ?1 AND service = ?2";
+    conn.execute(query, [name, service.unwrap_or("")])
+}
+
+pub fn set_command_env(
+    conn: &Connection,
+    command_id: i64,
+    name: &str,
+    value: &str,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO command_env (command_id, name, value) VALUES (?1, ?2, ?3)
+         ON CONFLICT (command_id, name) DO UPDATE SET value = excluded.value",
+        params![command_id, name, value],
+    )?;
+    Ok(())
+}
+
+pub fn retrieve_command_env(
+    conn: &Connection,
+    command_id: i64,
+) -> Result<Vec<CommandEnv>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT id, command_id, name, value FROM command_env WHERE command_id = ?1 ORDER BY name",
+    )?;
+
+    let data_iter = stmt.query_map([command_id], |row| {
+        Ok(CommandEnv {
+            id: row.get(0)?,
+            command_id: row.get(1)?,
+            name: row.get(2)?,
+            value: row.get(3)?,
+        })
+    })?;
+
+    let env: Vec<CommandEnv> = data_iter.filter_map(Result::ok).collect();
+
+    Ok(env)
+}
+
+pub fn unset_command_env(
+    conn: &Connection,
+    command_id: i64,
+    name: &str,
+) -> Result<usize, rusqlite::Error> {
+    let query = "DELETE FROM command_env WHERE command_id = ?1 AND name = ?2";
+    conn.execute(query, params![command_id, name])
+}
+
+pub fn set_setting(conn: &Connection, key: &str, value: &str) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+        [key, value],
+    )?;
+    Ok(())
+}
+
+pub fn get_setting(conn: &Connection, key: &str) -> Result<String, rusqlite::Error> {
+    let query = "SELECT value FROM settings WHERE key = ?1";
+    conn.query_row(query, [key], |row| row.get(0))
+}
+
+pub fn list_settings(conn: &Connection) -> Result<Vec<Setting>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT key, value FROM settings ORDER BY key")?;
+
+    let data_iter = stmt.query_map([], |row| {
+        Ok(Setting {
+            key: row.get(0)?,
+            value: row.get(1)?,
+        })
+    })?;
+
+    let settings: Vec<Setting> = data_iter.filter_map(Result::ok).collect();
+
+    Ok(settings)
+}
+
+pub fn unset_setting(conn: &Connection, key: &str) -> Result<usize, rusqlite::Error> {
+    conn.execute("DELETE FROM settings WHERE key = ?1", [key])
+}
+
+pub fn set_placeholder_generator(
+    conn: &Connection,
+    command_id: i64,
+    name: &str,
+    generator: &str,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO placeholders (command_id, name, generator) VALUES (?1, ?2, ?3)
+         ON CONFLICT (command_id, name) DO UPDATE SET generator = excluded.generator",
+        params![command_id, name, generator],
+    )?;
+    Ok(())
+}
+
+pub fn set_placeholder_column(
+    conn: &Connection,
+    command_id: i64,
+    name: &str,
+    column: i64,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO placeholders (command_id, name, generator_column) VALUES (?1, ?2, ?3)
+         ON CONFLICT (command_id, name) DO UPDATE SET generator_column = excluded.generator_column",
+        params![command_id, name, column],
+    )?;
+    Ok(())
+}
+
+pub fn set_placeholder_description(
+    conn: &Connection,
+    command_id: i64,
+    name: &str,
+    description: &str,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO placeholders (command_id, name, description) VALUES (?1, ?2, ?3)
+         ON CONFLICT (command_id, name) DO UPDATE SET description = excluded.description",
+        params![command_id, name, description],
+    )?;
+    Ok(())
+}
+
+pub fn retrieve_placeholder_specs(
+    conn: &Connection,
+    command_id: i64,
+) -> Result<Vec<PlaceholderSpec>, rusqlite::Error> {
+    let query = "SELECT id, command_id, name, generator, generator_column, description
+                 FROM placeholders WHERE command_id = ?1 ORDER BY id";
+    let mut stmt = conn.prepare(query)?;
+
+    let data_iter = stmt.query_map([command_id], |row| {
+        Ok(PlaceholderSpec {
+            id: row.get(0)?,
+            command_id: row.get(1)?,
+            name: row.get(2)?,
+            generator: row.get(3)?,
+            generator_column: row.get(4)?,
+            description: row.get(5)?,
+        })
+    })?;
+
+    let specs: Vec<PlaceholderSpec> = data_iter.filter_map(Result::ok).collect();
+
+    Ok(specs)
+}
+
+pub fn record_placeholder_value(
+    conn: &Connection,
+    command_id: i64,
+    name: &str,
+    value: &str,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO placeholder_history (command_id, name, value) VALUES (?1, ?2, ?3)
+         ON CONFLICT (command_id, name, value) DO UPDATE
+         SET uses = uses + 1, last_used = strftime('%s', 'now')",
+        params![command_id, name, value],
+    )?;
+    Ok(())
+}
+
+/// Remembered values, most frequently and recently used first.
+pub fn retrieve_placeholder_history(
+    conn: &Connection,
+    command_id: i64,
+    name: Option<&str>,
+) -> Result<Vec<PlaceholderValue>, rusqlite::Error> {
+    // Uses decay by the number of days since the value was last used
+    let query = "SELECT id, command_id, name, value, uses,
+                        datetime(last_used, 'unixepoch', 'localtime')
+                 FROM placeholder_history
+                 WHERE command_id = ?1 AND (?2 IS NULL OR name = ?2)
+                 ORDER BY uses / (1.0 + (strftime('%s', 'now') - last_used) / 86400.0) DESC,
+                          last_used DESC";
+    let mut stmt = conn.prepare(query)?;
+
+    let data_iter = stmt.query_map(params![command_id, name], |row| {
+        Ok(PlaceholderValue {
+            id: row.get(0)?,
+            command_id: row.get(1)?,
+            name: row.get(2)?,
+            value: row.get(3)?,
+            uses: row.get(4)?,
+            last_used: row.get(5)?,
+        })
+    })?;
+
+    let values: Vec<PlaceholderValue> = data_iter.filter_map(Result::ok).collect();
+
+    Ok(values)
+}
+
+pub fn forget_placeholder_values(
+    conn: &Connection,
+    command_id: i64,
+    name: Option<&str>,
+    value: Option<&str>,
+) -> Result<usize, rusqlite::Error> {
+    let query = "DELETE FROM placeholder_history
+                 WHERE command_id = ?1 AND (?2 IS NULL OR name = ?2) AND (?3 IS NULL OR value = ?3)";
+    conn.execute(query, params![command_id, name, value])
+}
+
+pub fn record_run(
+    conn: &Connection,
+    command_id: i64,
+    command: &str,
+    duration_ms: i64,
+    exit_code: Option<i32>,
+    output: Option<&str>,
+    attempt: i64,
+) -> Result<i64, rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO runs (command_id, command, duration_ms, exit_code, output, attempt)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![command_id, command, duration_ms, exit_code, output, attempt],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+const RUN_COLUMNS: &str = "runs.id, runs.command_id, commands.alias, runs.command,
+                           datetime(runs.started_at, 'unixepoch', 'localtime'),
+                           runs.duration_ms, runs.exit_code, runs.output, runs.attempt";
+
+fn run_from_row(row: &Row) -> Result<Run> {
+    Ok(Run {
+        id: row.get(0)?,
+        command_id: row.get(1)?,
+        alias: row.get(2)?,
+        command: row.get(3)?,
+        started_at: row.get(4)?,
+        duration_ms: row.get(5)?,
+        exit_code: row.get(6)?,
+        output: row.get(7)?,
+        attempt: row.get(8)?,
+    })
+}
+
+/// Most recent runs first, of one command or of all of them.
+pub fn retrieve_runs(
+    conn: &Connection,
+    command_id: Option<i64>,
+    limit: usize,
+) -> Result<Vec<Run>, rusqlite::Error> {
+    let query = format!(
+        "SELECT {} FROM runs JOIN commands ON commands.id = runs.command_id
+         WHERE ?1 IS NULL OR runs.command_id = ?1
+         ORDER BY runs.id DESC LIMIT ?2",
+        RUN_COLUMNS
+    );
+    let mut stmt = conn.prepare(&query)?;
+
+    let data_iter = stmt.query_map(params![command_id, limit as i64], run_from_row)?;
+    let runs: Vec<Run> = data_iter.filter_map(Result::ok).collect();
+
+    Ok(runs)
+}
+
+pub fn retrieve_run(conn: &Connection, id: i64) -> Result<Run, rusqlite::Error> {
+    let query = format!(
+        "SELECT {} FROM runs JOIN commands ON commands.id = runs.command_id WHERE runs.id = ?1",
+        RUN_COLUMNS
+    );
+    conn.query_row(&query, params![id], run_from_row)
+}
+
+pub fn set_schedule(
+    conn: &Connection,
+    command_id: i64,
+    cron: &str,
+    missed: &str,
+) -> Result<(), rusqlite::Error> {
+    // A changed schedule starts afresh, without runs missed under the old one
+    conn.execute(
+        "INSERT INTO schedules (command_id, cron, missed) VALUES (?1, ?2, ?3)
+         ON CONFLICT (command_id) DO UPDATE SET cron = excluded.cron,
+             missed = excluded.missed, last_run = NULL,
+             created_at = strftime('%s', 'now')",
+        params![command_id, cron, missed],
+    )?;
+    Ok(())
+}
+
+pub fn retrieve_schedules(conn: &Connection) -> Result<Vec<Schedule>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT schedules.id, schedules.command_id, commands.alias, schedules.cron,
+                schedules.missed, schedules.last_run, schedules.created_at
+         FROM schedules JOIN commands ON commands.id = schedules.command_id
+         ORDER BY commands.alias",
+    )?;
+
+    let data_iter = stmt.query_map([], |row| {
+        Ok(Schedule {
+            id: row.get(0)?,
+            command_id: row.get(1)?,
+            alias: row.get(2)?,
+            cron: row.get(3)?,
+            missed: row.get(4)?,
+            last_run: row.get(5)?,
+            created_at: row.get(6)?,
+        })
+    })?;
+
+    let schedules: Vec<Schedule> = data_iter.filter_map(Result::ok).collect();
+
+    Ok(schedules)
+}
+
+pub fn update_schedule_last_run(
+    conn: &Connection,
+    id: i64,
+    last_run: i64,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "UPDATE schedules SET last_run = ?2 WHERE id = ?1",
+        params![id, last_run],
+    )?;
+    Ok(())
+}
+
+pub fn delete_schedule(conn: &Connection, command_id: i64) -> Result<usize, rusqlite::Error> {
+    conn.execute(
+        "DELETE FROM schedules WHERE command_id = ?1",
+        params![command_id],
+    )
+}